@@ -110,12 +110,12 @@ class NLPAIAdapter extends AIInsightsComponent {
                 })
             });
 
-            if (!response.ok) {
+            // Failures (422 unparseable output, 502 provider error) still carry the usual JSON body
+            const result = await response.json().catch(() => null);
+            if (!result) {
                 throw new Error(`HTTP ${response.status}: ${response.statusText}`);
             }
 
-            const result = await response.json();
-
             // Display results (server already parsed and structured the response)
             if (result.success && result.matches) {
                 this.displaySearchResults(result, this.currentQuery);
@@ -1809,6 +1809,27 @@ async function checkDatabaseStatus() {
     checkIndividualDatabaseStatus();
 }
 
+// Admin endpoints require the server's ADMIN_KEY, kept in localStorage as 'admin_key'.
+// Prompts for it (with promptMessage) when nothing is stored; returns null if none is given.
+function getAdminKey(promptMessage) {
+    let adminKey = null;
+    try { adminKey = localStorage.getItem('admin_key'); } catch (e) { adminKey = null; }
+    if (!adminKey && promptMessage) {
+        adminKey = prompt(promptMessage);
+        if (adminKey) {
+            try { localStorage.setItem('admin_key', adminKey); } catch (e) {}
+        }
+    }
+    return adminKey || null;
+}
+
+// Drop a stored ADMIN_KEY after the server rejected it (401)
+function forgetAdminKey(response) {
+    if (response.status === 401) {
+        try { localStorage.removeItem('admin_key'); } catch (e) {}
+    }
+}
+
 // Make functions globally available
 // Function to stop Rust server
 async function stopRustServer() {
@@ -1820,13 +1841,18 @@ async function stopRustServer() {
     }
     
     try {
-        // Call the Rust API restart endpoint which performs a clean shutdown
+        const adminKey = getAdminKey('Enter the server ADMIN_KEY to stop the Rust server:');
+        if (!adminKey) throw new Error('ADMIN_KEY is required to stop the server');
+
+        // Call the Rust API restart endpoint which performs a graceful shutdown
         const response = await fetch('http://localhost:8081/api/config/restart', {
             method: 'POST',
             headers: {
-                'Content-Type': 'application/json'
+                'Content-Type': 'application/json',
+                'x-admin-key': adminKey
             }
         });
+        forgetAdminKey(response);
 
         if (response.ok) {
             const result = await response.json();
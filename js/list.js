@@ -117,6 +117,8 @@ async function storeFallbackCSV(csvText) {
             method: 'POST',
             headers: {
                 'Content-Type': 'application/json',
+                // Saving files needs the server's ADMIN_KEY; without one this falls back to localStorage
+                'x-admin-key': localStorage.getItem('admin_key') || '',
             },
             body: JSON.stringify({
                 filename: 'lists.csv',
@@ -0,0 +1,3 @@
+-- Databases created before priority was added to the projects table lack the column
+
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS priority VARCHAR(255);
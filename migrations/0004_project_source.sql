@@ -0,0 +1,8 @@
+-- Where imported projects came from and their id there, so feeds can be re-imported
+-- as updates (NULL external_ids never conflict)
+
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS source VARCHAR(50);
+
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS external_id VARCHAR(255);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_projects_source_external_id ON projects (source, external_id);
@@ -0,0 +1,16 @@
+-- Semantic search analytics (written only when SEARCH_ANALYTICS=on)
+
+CREATE TABLE IF NOT EXISTS search_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    query_text TEXT NOT NULL,
+    provider VARCHAR(50) NOT NULL,
+    success BOOLEAN NOT NULL DEFAULT false,
+    match_count INTEGER,
+    prompt_tokens INTEGER,
+    completion_tokens INTEGER,
+    total_tokens INTEGER,
+    user_hash VARCHAR(64),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_search_log_created_at ON search_log (created_at);
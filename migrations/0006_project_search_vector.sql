@@ -0,0 +1,10 @@
+-- Keyword search over name (weighted higher) and description. A stored generated column
+-- is filled for existing rows when it is added and kept current by Postgres afterwards.
+
+ALTER TABLE projects ADD COLUMN IF NOT EXISTS search_vector tsvector
+GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', coalesce(name, '')), 'A') ||
+    setweight(to_tsvector('english', coalesce(description, '')), 'B')
+) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_projects_search_vector ON projects USING GIN (search_vector);
@@ -0,0 +1,12 @@
+-- Author recorded in created_by/modified_user_id when no one is signed in. Handlers used to
+-- write the placeholder '1', which matches no user; point those rows at this one instead.
+-- The audit columns stay VARCHAR(36) like the rest of the CRM schema and hold users.id as text.
+
+INSERT INTO users (id, user_name, first_name, status)
+VALUES ('00000000-0000-0000-0000-000000000001', 'system', 'System', 'Active')
+ON CONFLICT (id) DO NOTHING;
+
+UPDATE projects SET created_by = '00000000-0000-0000-0000-000000000001' WHERE created_by = '1';
+UPDATE projects SET modified_user_id = '00000000-0000-0000-0000-000000000001' WHERE modified_user_id = '1';
+UPDATE contacts SET created_by = '00000000-0000-0000-0000-000000000001' WHERE created_by = '1';
+UPDATE contacts SET modified_user_id = '00000000-0000-0000-0000-000000000001' WHERE modified_user_id = '1';
@@ -0,0 +1,10 @@
+-- The importers recorded their own name ('excel-import', 'csv-import', 'json-import',
+-- 'democracylab-import') as the author, which matches no user. Point those rows at the
+-- system user seeded in 0007 instead.
+
+UPDATE projects SET created_by = '00000000-0000-0000-0000-000000000001'
+WHERE created_by IN ('excel-import', 'json-import', 'democracylab-import');
+UPDATE projects SET modified_user_id = '00000000-0000-0000-0000-000000000001'
+WHERE modified_user_id IN ('excel-import', 'json-import', 'democracylab-import');
+UPDATE accounts SET created_by = '00000000-0000-0000-0000-000000000001' WHERE created_by = 'csv-import';
+UPDATE accounts SET modified_user_id = '00000000-0000-0000-0000-000000000001' WHERE modified_user_id = 'csv-import';
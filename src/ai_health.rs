@@ -0,0 +1,256 @@
+// src/ai_health.rs
+// Cached health of the AI providers: last call outcome and a simple circuit breaker
+
+use actix_web::{web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Consecutive failures that open the breaker
+const FAILURE_THRESHOLD: u32 = 3;
+/// How long an open breaker rejects calls before letting one through
+const OPEN_COOLDOWN: Duration = Duration::from_secs(60);
+/// How long the detected Claude CLI install and version are reused before checking again
+const CLI_STATUS_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderSnapshot {
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    pub circuit_breaker: BreakerState,
+}
+
+struct ProviderState {
+    last_success: Option<DateTime<Utc>>,
+    last_failure: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Outcome tracking for one provider. Lives in a static so call sites in the
+/// provider modules can record results without access to ApiState.
+pub struct ProviderHealth {
+    state: Mutex<ProviderState>,
+}
+
+pub static GEMINI: ProviderHealth = ProviderHealth::new();
+pub static CLAUDE: ProviderHealth = ProviderHealth::new();
+
+impl ProviderHealth {
+    const fn new() -> Self {
+        ProviderHealth {
+            state: Mutex::new(ProviderState {
+                last_success: None,
+                last_failure: None,
+                last_error: None,
+                consecutive_failures: 0,
+                opened_at: None,
+            }),
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.last_success = Some(Utc::now());
+        state.consecutive_failures = 0;
+        state.opened_at = None;
+    }
+
+    pub fn record_failure(&self, error: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.last_failure = Some(Utc::now());
+        state.last_error = Some(error.chars().take(500).collect());
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= FAILURE_THRESHOLD {
+            // A failed half-open trial restarts the cooldown
+            state.opened_at = Some(Instant::now());
+        }
+    }
+
+    fn breaker_state(state: &ProviderState) -> BreakerState {
+        match state.opened_at {
+            None => BreakerState::Closed,
+            Some(opened_at) if opened_at.elapsed() < OPEN_COOLDOWN => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Seconds until an open breaker allows another attempt, or None if calls may proceed
+    pub fn retry_after(&self) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        match (Self::breaker_state(&state), state.opened_at) {
+            (BreakerState::Open, Some(opened_at)) => Some(OPEN_COOLDOWN.saturating_sub(opened_at.elapsed()).as_secs().max(1)),
+            _ => None,
+        }
+    }
+
+    pub fn snapshot(&self) -> ProviderSnapshot {
+        let state = self.state.lock().unwrap();
+        ProviderSnapshot {
+            last_success: state.last_success,
+            last_failure: state.last_failure,
+            last_error: state.last_error.clone(),
+            consecutive_failures: state.consecutive_failures,
+            circuit_breaker: Self::breaker_state(&state),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AiHealthQuery {
+    #[serde(default)]
+    pub probe: bool,
+}
+
+#[derive(Debug, Clone)]
+struct CliStatus {
+    installed: bool,
+    version: Option<String>,
+}
+
+static CLI_STATUS: Mutex<Option<(Instant, CliStatus)>> = Mutex::new(None);
+
+/// Whether the claude binary is on PATH and its version. Looking that up runs two child
+/// processes, so the answer is cached for CLI_STATUS_TTL rather than redone per request.
+async fn claude_cli_status() -> CliStatus {
+    if let Some((checked_at, status)) = CLI_STATUS.lock().unwrap().as_ref() {
+        if checked_at.elapsed() < CLI_STATUS_TTL {
+            return status.clone();
+        }
+    }
+
+    let installed = tokio::task::spawn_blocking(crate::claude_insights::is_cli_installed).await.unwrap_or(false);
+    let version = if installed { claude_cli_version().await } else { None };
+    let status = CliStatus { installed, version };
+    *CLI_STATUS.lock().unwrap() = Some((Instant::now(), status.clone()));
+    status
+}
+
+async fn claude_cli_version() -> Option<String> {
+    let output = tokio::process::Command::new("claude").arg("--version").output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!version.is_empty()).then_some(version)
+}
+
+/// GET /api/health/ai - cached provider status; `?probe=true` (admin only) makes live test calls
+pub async fn ai_health(
+    req: HttpRequest,
+    data: web::Data<std::sync::Arc<crate::ApiState>>,
+    query: web::Query<AiHealthQuery>,
+) -> Result<HttpResponse> {
+    if query.probe {
+        // Probes can be billable, so they are limited to operators
+        if let Some(denied) = crate::require_admin_key(&req) {
+            return Ok(denied);
+        }
+    }
+
+    let (gemini_key, gemini_model) = {
+        let config = data.config.lock().unwrap();
+        (config.gemini_api_key.clone(), config.gemini_model.clone())
+    };
+    let gemini_configured = crate::gemini_insights::is_api_key_configured(&gemini_key);
+    let claude_backend = crate::claude_insights::ClaudeBackend::from_state(&data);
+    let cli = claude_cli_status().await;
+    let claude_available = match claude_backend {
+        crate::claude_insights::ClaudeBackend::Cli => cli.installed,
+        _ => claude_backend.is_available(),
+    };
+
+    let mut gemini = json!({ "configured": gemini_configured });
+    let mut claude = json!({
+        "backend": claude_backend.name(),
+        "available": claude_available,
+        "installed": cli.installed,
+        "version": cli.version
+    });
+    if let crate::claude_insights::ClaudeBackend::Api { model, .. } = &claude_backend {
+        claude["model"] = json!(model);
+    }
+
+    if query.probe {
+        if gemini_configured {
+            let key = crate::gemini_insights::check_api_key(&gemini_key).unwrap_or_default().to_string();
+            let generation = crate::gemini_insights::GenerationConfig::default();
+            gemini["probe"] = match crate::gemini_insights::call_gemini_api(&data.http_client, &key, &gemini_model, "Reply with OK", generation).await {
+                Ok(_) => json!({ "success": true }),
+                Err(e) => json!({ "success": false, "error": e.to_string() }),
+            };
+        }
+        if claude_available {
+            claude["probe"] = match crate::claude_insights::call_claude(&data.http_client, &claude_backend, "Reply with OK", &None).await {
+                Ok(_) => json!({ "success": true }),
+                Err(e) => json!({ "success": false, "error": e.to_string() }),
+            };
+        }
+    }
+
+    let gemini_snapshot = GEMINI.snapshot();
+    let claude_snapshot = CLAUDE.snapshot();
+    for (target, snapshot) in [(&mut gemini, &gemini_snapshot), (&mut claude, &claude_snapshot)] {
+        if let (Some(target), Ok(serde_json::Value::Object(fields))) = (target.as_object_mut(), serde_json::to_value(snapshot)) {
+            target.extend(fields);
+        }
+    }
+
+    let usable = [
+        (gemini_configured, gemini_snapshot.circuit_breaker),
+        (claude_available, claude_snapshot.circuit_breaker),
+    ];
+    let status = if !usable.iter().any(|(available, _)| *available) {
+        "unavailable"
+    } else if usable.iter().any(|(available, breaker)| *available && *breaker == BreakerState::Open) {
+        "degraded"
+    } else {
+        "healthy"
+    };
+
+    Ok(HttpResponse::Ok().json(json!({
+        "status": status,
+        "providers": {
+            "gemini": gemini,
+            "claude": claude
+        }
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_after_repeated_failures_and_resets_on_success() {
+        let health = ProviderHealth::new();
+        assert_eq!(health.snapshot().circuit_breaker, BreakerState::Closed);
+
+        for _ in 0..FAILURE_THRESHOLD {
+            assert!(health.retry_after().is_none());
+            health.record_failure("HTTP 500");
+        }
+        let snapshot = health.snapshot();
+        assert_eq!(snapshot.circuit_breaker, BreakerState::Open);
+        assert_eq!(snapshot.consecutive_failures, FAILURE_THRESHOLD);
+        assert_eq!(snapshot.last_error.as_deref(), Some("HTTP 500"));
+        assert!(health.retry_after().is_some());
+
+        health.record_success();
+        assert_eq!(health.snapshot().circuit_breaker, BreakerState::Closed);
+        assert!(health.retry_after().is_none());
+    }
+}
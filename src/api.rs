@@ -0,0 +1,219 @@
+// src/api.rs
+// The JSON envelope handlers answer with, and the error type that renders into it, so
+// handlers can use `?` on database and HTTP client results
+
+use actix_web::body::BoxBody;
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, Responder, ResponseError};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// `{ success, data, error, code }`, plus any top-level fields a handler adds with `with`
+#[derive(Debug, Serialize)]
+pub struct ApiResponse<T: Serialize> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+    pub code: Option<&'static str>,
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+    #[serde(skip)]
+    status: StatusCode,
+}
+
+pub type ApiResult<T = Value> = Result<ApiResponse<T>, ApiError>;
+
+impl<T: Serialize> ApiResponse<T> {
+    pub fn ok(data: T) -> Self {
+        ApiResponse {
+            success: true,
+            data: Some(data),
+            error: None,
+            code: None,
+            extra: Map::new(),
+            status: StatusCode::OK,
+        }
+    }
+
+    pub fn created(data: T) -> Self {
+        ApiResponse { status: StatusCode::CREATED, ..Self::ok(data) }
+    }
+
+    /// Add a field next to `data`, such as a message or pagination totals
+    pub fn with(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.extra.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+impl<T: Serialize> Responder for ApiResponse<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse {
+        HttpResponse::build(self.status).json(&self)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    /// A bad request, optionally naming the request field at fault
+    #[error("{message}")]
+    BadRequest { message: String, field: Option<String> },
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Conflict(String),
+    /// No pool: 501 when the deployment runs without a database, 503 when the connection failed
+    #[error("{message}")]
+    DatabaseUnavailable { status: StatusCode, message: &'static str },
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    #[error(transparent)]
+    Upstream(#[from] reqwest::Error),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl ApiError {
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        ApiError::BadRequest { message: message.into(), field: None }
+    }
+
+    pub fn invalid_field(field: &str, message: impl Into<String>) -> Self {
+        ApiError::BadRequest { message: message.into(), field: Some(field.to_string()) }
+    }
+
+    /// The `error` text clients see. Server-side failures get a fixed message, since sqlx,
+    /// reqwest and anyhow errors can carry SQL, hostnames or URLs with keys in them; the
+    /// detail goes to the log instead.
+    fn public_message(&self) -> String {
+        match self {
+            ApiError::Database(_) if self.status_code().is_server_error() => "Database error".to_string(),
+            ApiError::Upstream(e) if e.is_timeout() => "Upstream service timed out".to_string(),
+            ApiError::Upstream(_) => "Upstream service request failed".to_string(),
+            ApiError::Internal(_) => "Internal server error".to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Machine-readable `code` in the envelope; clients should branch on this, not on `error`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest { field: Some(_), .. } => "invalid_field",
+            ApiError::BadRequest { .. } => "bad_request",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::DatabaseUnavailable { status, .. } if *status == StatusCode::NOT_IMPLEMENTED => "database_disabled",
+            ApiError::DatabaseUnavailable { .. } => "database_unavailable",
+            ApiError::Database(e) => match database_error_kind(e) {
+                Some(sqlx::error::ErrorKind::UniqueViolation) => "conflict",
+                Some(_) => "invalid_data",
+                None if matches!(e, sqlx::Error::RowNotFound) => "not_found",
+                None => "database_error",
+            },
+            ApiError::Upstream(e) if e.is_timeout() => "upstream_timeout",
+            ApiError::Upstream(_) => "upstream_error",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+}
+
+// Constraint violations are the caller's data, not a server fault
+fn database_error_kind(error: &sqlx::Error) -> Option<sqlx::error::ErrorKind> {
+    match error {
+        sqlx::Error::Database(e) => match e.kind() {
+            sqlx::error::ErrorKind::Other => None,
+            kind => Some(kind),
+        },
+        _ => None,
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::DatabaseUnavailable { status, .. } => *status,
+            ApiError::Database(_) => match self.code() {
+                "conflict" => StatusCode::CONFLICT,
+                "invalid_data" => StatusCode::BAD_REQUEST,
+                "not_found" => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+            ApiError::Upstream(e) if e.is_timeout() => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        if status.is_server_error() {
+            log::error!("{}: {self:#}", self.code());
+        }
+        let mut body = ApiResponse::<Value> {
+            success: false,
+            data: None,
+            error: Some(self.public_message()),
+            code: Some(self.code()),
+            extra: Map::new(),
+            status,
+        };
+        if let ApiError::BadRequest { field: Some(field), .. } = self {
+            body = body.with("field", field.as_str());
+        }
+        HttpResponse::build(status).json(&body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn body_json(response: HttpResponse) -> Value {
+        let bytes = actix_web::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_errors_render_the_envelope() {
+        let error = ApiError::invalid_field("status", "Invalid status 'x'");
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body_json(error.error_response()).await,
+            json!({ "success": false, "data": null, "error": "Invalid status 'x'", "code": "invalid_field", "field": "status" })
+        );
+
+        let error = ApiError::from(sqlx::Error::RowNotFound);
+        assert_eq!(error.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(error.code(), "not_found");
+
+        let error = ApiError::DatabaseUnavailable { status: StatusCode::NOT_IMPLEMENTED, message: "disabled" };
+        assert_eq!(error.status_code(), StatusCode::NOT_IMPLEMENTED);
+        assert_eq!(error.code(), "database_disabled");
+
+        let error = ApiError::from(anyhow::anyhow!("connect to 10.0.0.5 failed"));
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body_json(error.error_response()).await["error"], json!("Internal server error"));
+
+        let error = ApiError::from(sqlx::Error::Protocol("relation \"secret_table\" is broken".to_string()));
+        assert_eq!(body_json(error.error_response()).await["error"], json!("Database error"));
+    }
+
+    #[test]
+    fn test_success_envelope_keeps_extra_fields() {
+        let response = ApiResponse::created(json!({ "id": 7 })).with("message", "Created");
+        assert_eq!(response.status, StatusCode::CREATED);
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({ "success": true, "data": { "id": 7 }, "error": null, "code": null, "message": "Created" })
+        );
+    }
+}
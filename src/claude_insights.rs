@@ -1,7 +1,63 @@
 // src/claude_insights.rs
+// Claude analysis through the Anthropic Messages API or the local `claude` CLI
 use actix_web::{web, HttpResponse, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use anyhow::Context;
+use crate::prompts::{build_data_analysis_prompt, check_prompt_size};
+use crate::ApiState;
+
+const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+// Enough for a detailed analysis; longer answers stop with stop_reason "max_tokens"
+const MAX_OUTPUT_TOKENS: u32 = 4096;
+
+pub const API_KEY_SETUP_HINT: &str = "Create a key at https://console.anthropic.com/settings/keys and set ANTHROPIC_API_KEY in your .env file";
+
+/// Where Claude requests go: the Messages API, or the `claude` binary on this machine
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClaudeBackend {
+    Api { api_key: String, model: String },
+    Cli,
+}
+
+impl ClaudeBackend {
+    /// CLAUDE_BACKEND=api or cli forces one; otherwise the API when ANTHROPIC_API_KEY is set
+    pub fn select(backend: &str, api_key: &str, model: &str) -> Self {
+        let api = || ClaudeBackend::Api { api_key: api_key.trim().to_string(), model: model.trim().to_string() };
+        match backend.trim().to_lowercase().as_str() {
+            "api" => api(),
+            "cli" => ClaudeBackend::Cli,
+            _ if is_api_key_configured(api_key) => api(),
+            _ => ClaudeBackend::Cli,
+        }
+    }
+
+    pub fn from_state(state: &ApiState) -> Self {
+        let config = state.config.lock().unwrap();
+        Self::select(&config.claude_backend, &config.anthropic_api_key, &config.claude_model)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClaudeBackend::Api { .. } => "api",
+            ClaudeBackend::Cli => "cli",
+        }
+    }
+
+    /// A key for the API, or the binary on PATH for the CLI
+    pub fn is_available(&self) -> bool {
+        match self {
+            ClaudeBackend::Api { api_key, .. } => is_api_key_configured(api_key),
+            ClaudeBackend::Cli => is_cli_installed(),
+        }
+    }
+}
+
+// True when ANTHROPIC_API_KEY holds something other than a blank or placeholder value
+pub fn is_api_key_configured(api_key: &str) -> bool {
+    !crate::is_placeholder_value(api_key)
+}
 
 #[derive(Debug, Deserialize)]
 pub struct ClaudeAnalysisRequest {
@@ -17,17 +73,47 @@ pub struct ClaudeAnalysisResponse {
     pub token_usage: Option<TokenUsage>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, PartialEq)]
 pub struct TokenUsage {
     pub prompt_tokens: Option<u32>,
     pub completion_tokens: Option<u32>,
     pub total_tokens: Option<u32>,
+    /// True when the counts are guessed from text length rather than reported by Claude
+    pub estimated: bool,
+}
+
+impl TokenUsage {
+    // Rough estimate: 4 chars per token
+    fn estimate(prompt: &str, response: &str) -> Self {
+        Self::estimate_tokens((prompt.len() / 4) as u32, (response.len() / 4) as u32)
+    }
+
+    fn estimate_tokens(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        TokenUsage {
+            prompt_tokens: Some(prompt_tokens),
+            completion_tokens: Some(completion_tokens),
+            total_tokens: Some(prompt_tokens + completion_tokens),
+            estimated: true,
+        }
+    }
 }
 
-pub async fn analyze_with_claude_cli(
+pub async fn analyze_with_claude(
+    data: web::Data<std::sync::Arc<ApiState>>,
     req: web::Json<ClaudeAnalysisRequest>,
 ) -> Result<HttpResponse> {
-    match call_claude_code_cli(&req.prompt, &req.dataset_info).await {
+    let max_prompt_chars = data.config.lock().unwrap().max_prompt_chars;
+    if let Err(reason) = check_prompt_size(&full_prompt(&req.prompt, &req.dataset_info), max_prompt_chars) {
+        return Ok(HttpResponse::PayloadTooLarge().json(ClaudeAnalysisResponse {
+            success: false,
+            analysis: None,
+            error: Some(reason),
+            token_usage: None,
+        }));
+    }
+
+    let backend = ClaudeBackend::from_state(&data);
+    match call_claude(&data.http_client, &backend, &req.prompt, &req.dataset_info).await {
         Ok((analysis, token_usage)) => Ok(HttpResponse::Ok().json(ClaudeAnalysisResponse {
             success: true,
             analysis: Some(analysis),
@@ -35,63 +121,113 @@ pub async fn analyze_with_claude_cli(
             token_usage,
         })),
         Err(e) => {
-            eprintln!("Claude Code CLI Error: {e:?}");
-            
-            // Provide estimated token usage even when Claude CLI fails
-            let prompt_len = req.prompt.len();
-            let estimated_prompt_tokens = (prompt_len / 4) as u32;
-            let estimated_completion_tokens = 50; // Rough estimate for fallback message
-            let estimated_total = estimated_prompt_tokens + estimated_completion_tokens;
+            log::error!("Claude {} error: {e:?}", backend.name());
             
-            let fallback_token_usage = Some(TokenUsage {
-                prompt_tokens: Some(estimated_prompt_tokens),
-                completion_tokens: Some(estimated_completion_tokens),
-                total_tokens: Some(estimated_total),
-            });
+            // Provide estimated token usage even when the Claude call fails
+            // (50 completion tokens is a rough estimate for the fallback message)
+            let fallback_token_usage = Some(TokenUsage::estimate_tokens((req.prompt.len() / 4) as u32, 50));
             
             Ok(HttpResponse::InternalServerError().json(ClaudeAnalysisResponse {
                 success: false,
                 analysis: None,
-                error: Some(format!("Claude Code CLI execution failed: {e}")),
+                error: Some(match backend {
+                    ClaudeBackend::Api { .. } => format!("Claude API request failed: {e}"),
+                    ClaudeBackend::Cli => format!("Claude Code CLI execution failed: {e}"),
+                }),
                 token_usage: fallback_token_usage,
             }))
         }
     }
 }
 
-// Call Claude Code CLI for dataset analysis
-pub async fn call_claude_code_cli(prompt: &str, dataset_info: &Option<serde_json::Value>) -> anyhow::Result<(String, Option<TokenUsage>)> {
+// The prompt as sent to Claude, with any dataset context appended
+fn full_prompt(prompt: &str, dataset_info: &Option<serde_json::Value>) -> String {
+    match dataset_info {
+        Some(dataset) => build_data_analysis_prompt(prompt, dataset),
+        None => prompt.to_string(),
+    }
+}
+
+// Check if the claude command is available on PATH
+pub fn is_cli_installed() -> bool {
     use std::process::Command;
 
-    // Check if claude command exists
     let check_command = if cfg!(target_os = "windows") {
         Command::new("where").arg("claude").output()
     } else {
         Command::new("which").arg("claude").output()
     };
 
-    if let Ok(check_result) = check_command {
-        if !check_result.status.success() {
-            return Err(anyhow::anyhow!(
-                "Claude CLI not installed. To use this feature, install the Claude CLI or use the Gemini API instead."
-            ));
+    // If the lookup tool itself is unavailable, assume installed and let the real call report errors
+    check_command.map(|result| result.status.success()).unwrap_or(true)
+}
+
+// Ask Claude through the selected backend, recording the outcome for /api/health/ai
+pub async fn call_claude(
+    client: &reqwest::Client,
+    backend: &ClaudeBackend,
+    prompt: &str,
+    dataset_info: &Option<serde_json::Value>,
+) -> anyhow::Result<(String, Option<TokenUsage>)> {
+    let result = match backend {
+        ClaudeBackend::Api { api_key, model } => {
+            if !is_api_key_configured(api_key) {
+                anyhow::bail!("CLAUDE_BACKEND is api but ANTHROPIC_API_KEY is not set. {API_KEY_SETUP_HINT}");
+            }
+            request_messages_at(client, ANTHROPIC_BASE_URL, api_key, model, &full_prompt(prompt, dataset_info))
+                .await
+                .map(|(analysis, usage)| (analysis, Some(usage)))
+        }
+        ClaudeBackend::Cli => run_claude_cli(prompt, dataset_info).await,
+    };
+    match &result {
+        Ok((_, usage)) => {
+            crate::ai_health::CLAUDE.record_success();
+            if let Some(usage) = usage {
+                crate::metrics::METRICS.record_tokens("claude", usage.prompt_tokens, usage.completion_tokens);
+            }
         }
+        Err(e) => crate::ai_health::CLAUDE.record_failure(&e.to_string()),
     }
+    result
+}
 
-    // Build the full prompt with dataset context
-    let full_prompt = if let Some(dataset) = dataset_info {
-        format!("{}\n\nDataset Context:\n{}", prompt, serde_json::to_string_pretty(dataset)?)
-    } else {
-        prompt.to_string()
-    };
+// JSON output carries the exact token usage alongside the answer
+const CLI_ARGS: [&str; 3] = ["--print", "--output-format", "json"];
 
-    println!("Executing Claude Code CLI analysis...");
+// Run `program args...` with the prompt written to stdin rather than passed as an argument,
+// so a prompt starting with "-" can't be read as a flag
+async fn run_with_prompt_on_stdin(program: &str, args: &[&str], prompt: &str) -> std::io::Result<std::process::Output> {
+    use std::process::Stdio;
+    use tokio::io::AsyncWriteExt;
+
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    stdin.write_all(prompt.as_bytes()).await?;
+    // Closing stdin tells the CLI the prompt is complete
+    drop(stdin);
+    child.wait_with_output().await
+}
 
-    // First try with regular text output since JSON format has issues
-    let output = Command::new("claude")
-        .arg("--print")
-        .arg(&full_prompt)
-        .output()
+async fn run_claude_cli(prompt: &str, dataset_info: &Option<serde_json::Value>) -> anyhow::Result<(String, Option<TokenUsage>)> {
+    if !is_cli_installed() {
+        return Err(anyhow::anyhow!(
+            "Claude CLI not installed. To use this feature, install the Claude CLI or use the Gemini API instead."
+        ));
+    }
+
+    let full_prompt = full_prompt(prompt, dataset_info);
+
+    log::debug!("Executing Claude Code CLI analysis...");
+
+    let output = run_with_prompt_on_stdin("claude", &CLI_ARGS, &full_prompt)
+        .await
         .context("Failed to execute claude command. Make sure Claude Code CLI is installed and accessible.")?;
     
     if !output.status.success() {
@@ -100,26 +236,200 @@ pub async fn call_claude_code_cli(prompt: &str, dataset_info: &Option<serde_json
     }
     
     let stdout = String::from_utf8_lossy(&output.stdout);
-    let analysis = stdout.trim().to_string();
+    let (analysis, token_usage) = parse_cli_output(&full_prompt, &stdout)?;
     
+    log::debug!("Claude Code CLI analysis completed successfully");
+    Ok((analysis, Some(token_usage)))
+}
+
+async fn request_messages_at(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: &str,
+    model: &str,
+    prompt: &str,
+) -> anyhow::Result<(String, TokenUsage)> {
+    let response = client
+        .post(format!("{base_url}/messages"))
+        .header("x-api-key", api_key.trim())
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .json(&json!({
+            "model": model,
+            "max_tokens": MAX_OUTPUT_TOKENS,
+            "messages": [{ "role": "user", "content": prompt }],
+        }))
+        .timeout(std::time::Duration::from_secs(120))
+        .send()
+        .await
+        .context("Failed to make request to Anthropic API")?;
+
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.context("Failed to parse Anthropic API response")?;
+    if !status.is_success() {
+        let message = body["error"]["message"].as_str().unwrap_or("No details");
+        anyhow::bail!("Anthropic API error {status}: {message}");
+    }
+
+    let analysis = body["content"]
+        .as_array()
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter(|block| block["type"] == "text")
+                .filter_map(|block| block["text"].as_str())
+                .collect::<String>()
+        })
+        .unwrap_or_default()
+        .trim()
+        .to_string();
     if analysis.is_empty() {
-        return Err(anyhow::anyhow!("Claude Code CLI returned empty response"));
+        anyhow::bail!("Anthropic API returned no text (stop_reason {})", body["stop_reason"]);
     }
+    if body["stop_reason"] == "max_tokens" {
+        log::warn!("Claude analysis stopped at the {MAX_OUTPUT_TOKENS} output token limit");
+    }
+
+    let usage = &body["usage"];
+    let token_usage = match (usage["input_tokens"].as_u64(), usage["output_tokens"].as_u64()) {
+        (Some(input), Some(output)) => TokenUsage {
+            prompt_tokens: Some(input as u32),
+            completion_tokens: Some(output as u32),
+            total_tokens: Some((input + output) as u32),
+            estimated: false,
+        },
+        _ => TokenUsage::estimate(prompt, &analysis),
+    };
+    Ok((analysis, token_usage))
+}
+
+// Read the answer and exact usage from `--output-format json` output. Output that
+// isn't the expected JSON is taken as the plain-text answer with estimated usage.
+fn parse_cli_output(prompt: &str, stdout: &str) -> anyhow::Result<(String, TokenUsage)> {
+    let stdout = stdout.trim();
+    let parsed = serde_json::from_str::<serde_json::Value>(stdout)
+        .ok()
+        .filter(|json| json["result"].is_string());
     
-    // Estimate token usage based on text length
-    let prompt_len = full_prompt.len();
-    let response_len = analysis.len();
-    
-    let estimated_prompt_tokens = (prompt_len / 4) as u32;  // Rough estimate: 4 chars per token
-    let estimated_completion_tokens = (response_len / 4) as u32;
-    let estimated_total = estimated_prompt_tokens + estimated_completion_tokens;
-    
-    let token_usage = Some(TokenUsage {
-        prompt_tokens: Some(estimated_prompt_tokens),
-        completion_tokens: Some(estimated_completion_tokens),
-        total_tokens: Some(estimated_total),
-    });
+    let (analysis, token_usage) = match parsed {
+        Some(json) => {
+            let analysis = json["result"].as_str().unwrap_or_default().trim().to_string();
+            if json["is_error"].as_bool().unwrap_or(false) {
+                return Err(anyhow::anyhow!("Claude Code CLI reported an error: {}", analysis));
+            }
+            let usage = &json["usage"];
+            let token_usage = match (usage["input_tokens"].as_u64(), usage["output_tokens"].as_u64()) {
+                (Some(input), Some(output)) => TokenUsage {
+                    prompt_tokens: Some(input as u32),
+                    completion_tokens: Some(output as u32),
+                    total_tokens: Some((input + output) as u32),
+                    estimated: false,
+                },
+                _ => TokenUsage::estimate(prompt, &analysis),
+            };
+            (analysis, token_usage)
+        }
+        None => (stdout.to_string(), TokenUsage::estimate(prompt, stdout)),
+    };
     
-    println!("Claude Code CLI analysis completed successfully");
+    if analysis.is_empty() {
+        return Err(anyhow::anyhow!("Claude Code CLI returned empty response"));
+    }
     Ok((analysis, token_usage))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_output_gives_exact_usage() {
+        let stdout = r#"{"type":"result","subtype":"success","is_error":false,"result":"Sales peak in Q3.","usage":{"input_tokens":1234,"cache_read_input_tokens":50,"output_tokens":87}}"#;
+        let (analysis, usage) = parse_cli_output("Summarize the dataset", stdout).unwrap();
+        assert_eq!(analysis, "Sales peak in Q3.");
+        assert_eq!(usage, TokenUsage {
+            prompt_tokens: Some(1234),
+            completion_tokens: Some(87),
+            total_tokens: Some(1321),
+            estimated: false,
+        });
+
+        // Plain text falls back to the length-based estimate
+        let (analysis, usage) = parse_cli_output("12345678", "Plain answer").unwrap();
+        assert_eq!(analysis, "Plain answer");
+        assert!(usage.estimated);
+        assert_eq!(usage.prompt_tokens, Some(2));
+
+        assert!(parse_cli_output("p", r#"{"result":"Credit balance is too low","is_error":true}"#).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cli_prompt_goes_to_stdin_not_argv() {
+        // Stand-in for the claude binary: echo each argument, then whatever arrives on stdin
+        let script = r#"for arg in "$@"; do printf 'arg:%s\n' "$arg"; done; printf 'stdin:'; cat"#;
+        let mut args = vec!["-c", script, "claude"];
+        args.extend(CLI_ARGS);
+
+        let prompt = "--dangerous --output-format text\nSummarize the data";
+        let output = run_with_prompt_on_stdin("sh", &args, prompt).await.unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            format!("arg:--print\narg:--output-format\narg:json\nstdin:{prompt}")
+        );
+    }
+
+    #[test]
+    fn test_backend_defaults_to_api_when_a_key_is_set() {
+        let api = ClaudeBackend::Api { api_key: "sk-ant-test".to_string(), model: "claude-sonnet-4-5".to_string() };
+        assert_eq!(ClaudeBackend::select("", "sk-ant-test", "claude-sonnet-4-5"), api);
+        assert_eq!(ClaudeBackend::select("", "", "claude-sonnet-4-5"), ClaudeBackend::Cli);
+        assert_eq!(ClaudeBackend::select("CLI", "sk-ant-test", "claude-sonnet-4-5"), ClaudeBackend::Cli);
+        assert_eq!(ClaudeBackend::select("api", "sk-ant-test", "claude-sonnet-4-5"), api);
+        assert!(!ClaudeBackend::select("api", "", "claude-sonnet-4-5").is_available());
+    }
+
+    #[tokio::test]
+    async fn test_messages_api_returns_reported_usage() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/messages")
+            .match_header("x-api-key", "sk-ant-test")
+            .match_header("anthropic-version", ANTHROPIC_VERSION)
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "model": "claude-sonnet-4-5",
+                "messages": [{ "role": "user", "content": "Summarize the dataset" }]
+            })))
+            .with_body(
+                json!({
+                    "type": "message",
+                    "content": [{ "type": "text", "text": "Sales peak " }, { "type": "text", "text": "in Q3." }],
+                    "stop_reason": "end_turn",
+                    "usage": { "input_tokens": 310, "output_tokens": 42 }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let (analysis, usage) = request_messages_at(&reqwest::Client::new(), &server.url(), "sk-ant-test", "claude-sonnet-4-5", "Summarize the dataset").await.unwrap();
+        mock.assert_async().await;
+        assert_eq!(analysis, "Sales peak in Q3.");
+        assert_eq!(usage, TokenUsage {
+            prompt_tokens: Some(310),
+            completion_tokens: Some(42),
+            total_tokens: Some(352),
+            estimated: false,
+        });
+
+        server.reset();
+        server
+            .mock("POST", "/messages")
+            .with_status(401)
+            .with_body(json!({ "type": "error", "error": { "type": "authentication_error", "message": "invalid x-api-key" } }).to_string())
+            .create_async()
+            .await;
+        let err = request_messages_at(&reqwest::Client::new(), &server.url(), "sk-ant-bad", "claude-sonnet-4-5", "p").await.unwrap_err();
+        assert!(err.to_string().contains("invalid x-api-key"));
+    }
+}
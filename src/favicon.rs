@@ -0,0 +1,185 @@
+// src/favicon.rs
+// Image proxy for favicons and logos, so the UI avoids CORS and mixed-content issues
+
+use actix_web::{web, HttpResponse, Result};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+use crate::{proxy_policy, ApiState};
+
+/// Largest image we will proxy
+pub const FAVICON_MAX_BYTES: usize = 512 * 1024;
+/// How long a fetched image is served from memory
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Entries kept before the oldest is evicted
+const CACHE_MAX_ENTRIES: usize = 500;
+
+#[derive(Debug, Deserialize)]
+pub struct FaviconQuery {
+    pub url: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedImage {
+    pub content_type: String,
+    pub bytes: web::Bytes,
+    fetched_at: Instant,
+}
+
+/// In-memory cache of proxied images keyed by URL
+#[derive(Default)]
+pub struct FaviconCache {
+    entries: Mutex<HashMap<String, CachedImage>>,
+}
+
+impl FaviconCache {
+    fn get(&self, url: &str) -> Option<CachedImage> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(url).filter(|image| image.fetched_at.elapsed() < CACHE_TTL).cloned()
+    }
+
+    fn insert(&self, url: String, image: CachedImage) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, cached| cached.fetched_at.elapsed() < CACHE_TTL);
+        if entries.len() >= CACHE_MAX_ENTRIES {
+            if let Some(oldest) = entries.iter().min_by_key(|(_, cached)| cached.fetched_at).map(|(key, _)| key.clone()) {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(url, image);
+    }
+}
+
+/// Check a proxy target with the same rules as the generic proxy: http(s) only, within
+/// PROXY_ALLOWED_HOSTS when set, and not resolving to a loopback/private address
+pub async fn validate_proxy_url(raw_url: &str, allowed_hosts: &[String], allow_private: bool) -> std::result::Result<Url, String> {
+    proxy_policy::check_destination(raw_url, allowed_hosts, allow_private)
+        .await
+        .map_err(|denied| denied.to_string())?;
+    Url::parse(raw_url).map_err(|e| format!("Invalid URL: {e}"))
+}
+
+/// Fetch an image, rejecting non-image content types and bodies over `max_bytes`
+pub async fn fetch_image(client: &reqwest::Client, url: &Url, max_bytes: usize) -> std::result::Result<CachedImage, String> {
+    let mut response = client
+        .get(url.clone())
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| format!("Request failed: {e}"))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Upstream server error: {}", response.status()));
+    }
+
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|ct| ct.to_str().ok())
+        .unwrap_or("")
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_lowercase();
+    if !content_type.starts_with("image/") {
+        return Err(format!("Not an image (content-type '{content_type}')"));
+    }
+
+    if response.content_length().is_some_and(|len| len as usize > max_bytes) {
+        return Err(format!("Image exceeds {} KB limit", max_bytes / 1024));
+    }
+
+    // Content-Length can be absent or wrong, so enforce the cap while reading
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| format!("Failed to read image: {e}"))? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(format!("Image exceeds {} KB limit", max_bytes / 1024));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(CachedImage {
+        content_type,
+        bytes: web::Bytes::from(body),
+        fetched_at: Instant::now(),
+    })
+}
+
+fn image_response(image: CachedImage) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(image.content_type)
+        .insert_header(("Cache-Control", "public, max-age=86400"))
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        // SVGs can carry script; keep them inert when opened directly
+        .insert_header(("Content-Security-Policy", "default-src 'none'; style-src 'unsafe-inline'"))
+        .body(image.bytes)
+}
+
+/// GET /api/proxy/favicon?url=
+pub async fn proxy_favicon(
+    data: web::Data<std::sync::Arc<ApiState>>,
+    query: web::Query<FaviconQuery>,
+) -> Result<HttpResponse> {
+    let (allowed_hosts, allow_private) = {
+        let config_guard = data.config.lock().unwrap();
+        (config_guard.proxy_allowed_hosts.clone(), config_guard.proxy_allow_private)
+    };
+    let url = match validate_proxy_url(&query.url, &allowed_hosts, allow_private).await {
+        Ok(url) => url,
+        Err(e) => return Ok(HttpResponse::BadRequest().json(json!({ "error": e }))),
+    };
+
+    if let Some(image) = data.favicon_cache.get(url.as_str()) {
+        return Ok(image_response(image));
+    }
+
+    let _permit = data.outbound_limit.acquire().await.map_err(actix_web::error::ErrorServiceUnavailable)?;
+    // Redirects and the connected address are held to the same checks as the URL itself
+    let client = proxy_policy::guarded_client(&allowed_hosts, allow_private).map_err(actix_web::error::ErrorInternalServerError)?;
+    match fetch_image(&client, &url, FAVICON_MAX_BYTES).await {
+        Ok(image) => {
+            data.favicon_cache.insert(url.to_string(), image.clone());
+            Ok(image_response(image))
+        }
+        Err(e) => Ok(HttpResponse::BadGateway().json(json!({ "error": e }))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_validate_proxy_url_blocks_internal_hosts() {
+        assert!(validate_proxy_url("https://93.184.216.34/favicon.ico", &[], false).await.is_ok());
+        assert!(validate_proxy_url("ftp://example.com/favicon.ico", &[], false).await.is_err());
+        assert!(validate_proxy_url("http://localhost:8081/api/health", &[], false).await.is_err());
+        assert!(validate_proxy_url("http://127.0.0.1/", &[], false).await.is_err());
+        assert!(validate_proxy_url("http://10.0.0.5/logo.png", &[], false).await.is_err());
+        assert!(validate_proxy_url("http://169.254.169.254/latest/meta-data", &[], false).await.is_err());
+        assert!(validate_proxy_url("http://[::1]/", &[], false).await.is_err());
+        assert!(validate_proxy_url("https://93.184.216.34/favicon.ico", &["example.org".to_string()], false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_image_checks_type_and_size() {
+        let mut server = mockito::Server::new_async().await;
+        server.mock("GET", "/icon.png").with_header("content-type", "image/png").with_body(vec![0u8; 64]).create_async().await;
+        server.mock("GET", "/page").with_header("content-type", "text/html").with_body("<html></html>").create_async().await;
+        server.mock("GET", "/huge.png").with_header("content-type", "image/png").with_body(vec![0u8; 2048]).create_async().await;
+
+        let client = reqwest::Client::new();
+        let base = Url::parse(&server.url()).unwrap();
+
+        let image = fetch_image(&client, &base.join("/icon.png").unwrap(), 1024).await.unwrap();
+        assert_eq!(image.content_type, "image/png");
+        assert_eq!(image.bytes.len(), 64);
+
+        assert!(fetch_image(&client, &base.join("/page").unwrap(), 1024).await.unwrap_err().contains("Not an image"));
+        assert!(fetch_image(&client, &base.join("/huge.png").unwrap(), 1024).await.unwrap_err().contains("limit"));
+    }
+}
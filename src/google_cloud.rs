@@ -0,0 +1,416 @@
+// src/google_cloud.rs
+// Service account authentication and permission preflight checks for Google APIs
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const RESOURCE_MANAGER_BASE_URL: &str = "https://cloudresourcemanager.googleapis.com/v3";
+/// v1 still reports lifecycleState and the organization/folder parent on project listings
+pub const RESOURCE_MANAGER_V1_BASE_URL: &str = "https://cloudresourcemanager.googleapis.com/v1";
+pub const CLOUD_BILLING_BASE_URL: &str = "https://cloudbilling.googleapis.com/v1";
+pub const SHEETS_BASE_URL: &str = "https://sheets.googleapis.com/v4";
+
+pub const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const SHEETS_READONLY_SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets.readonly";
+
+/// Cached access tokens are refreshed this long before Google expires them
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Permission needed on the parent organization/folder to create projects
+pub const PROJECT_CREATE_PERMISSION: &str = "resourcemanager.projects.create";
+/// Permission needed on a billing account to link it to a new project
+pub const BILLING_LINK_PERMISSION: &str = "billing.resourceAssociations.create";
+
+/// Fields of a downloaded service account JSON key that we rely on
+#[derive(Debug, Deserialize)]
+pub struct ServiceAccountKey {
+    #[serde(rename = "type")]
+    pub key_type: String,
+    pub client_email: String,
+    pub private_key: String,
+    #[serde(default = "default_token_uri")]
+    pub token_uri: String,
+}
+
+/// The only token endpoint a key may name; keys arrive with requests, and the server
+/// POSTs a signed assertion to this URI
+const GOOGLE_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+
+fn default_token_uri() -> String {
+    GOOGLE_TOKEN_URI.to_string()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PreflightError {
+    #[error("Service account key is invalid: {0}")]
+    InvalidKey(String),
+
+    #[error("Could not obtain an access token for {account}: {reason}")]
+    Token { account: String, reason: String },
+
+    #[error("Service account {account} is missing {} permission on {resource}", permissions.join(", "))]
+    MissingPermissions {
+        account: String,
+        resource: String,
+        permissions: Vec<String>,
+    },
+
+    #[error("Service account {account} cannot read spreadsheet {spreadsheet_id}. Share the sheet with {account}.")]
+    SpreadsheetAccess { account: String, spreadsheet_id: String },
+
+    #[error("Permission check failed: {0}")]
+    Request(String),
+}
+
+/// A bearer token and when Google stops accepting it
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: Instant,
+}
+
+/// Access tokens per key and scope, reused until shortly before they expire. Keys arrive with
+/// requests, so entries are keyed by the whole key rather than the account email it claims.
+#[derive(Clone, Default)]
+pub struct TokenCache {
+    tokens: Arc<tokio::sync::Mutex<HashMap<(String, String), AccessToken>>>,
+}
+
+impl TokenCache {
+    /// Bearer token for `key` with `scope`, minting a new one only when the cached
+    /// token is missing or about to expire
+    pub async fn token(&self, client: &reqwest::Client, key: &ServiceAccountKey, scope: &str) -> Result<String, PreflightError> {
+        self.get_or_fetch((key.fingerprint(), scope.to_string()), || key.fetch_token(client, scope))
+            .await
+    }
+
+    async fn get_or_fetch<F, Fut>(&self, cache_key: (String, String), fetch: F) -> Result<String, PreflightError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<AccessToken, PreflightError>>,
+    {
+        // Held across the fetch so concurrent requests wait for one token instead of each minting their own
+        let mut tokens = self.tokens.lock().await;
+        if let Some(cached) = tokens.get(&cache_key) {
+            if Instant::now() + TOKEN_REFRESH_MARGIN < cached.expires_at {
+                return Ok(cached.token.clone());
+            }
+        }
+        let fresh = fetch().await?;
+        let token = fresh.token.clone();
+        tokens.insert(cache_key, fresh);
+        Ok(token)
+    }
+}
+
+#[derive(Serialize)]
+struct JwtClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+impl ServiceAccountKey {
+    pub fn from_json(key_json: &str) -> Result<Self, PreflightError> {
+        let key: ServiceAccountKey = serde_json::from_str(key_json)
+            .map_err(|e| PreflightError::InvalidKey(e.to_string()))?;
+        if key.key_type != "service_account" {
+            return Err(PreflightError::InvalidKey(format!(
+                "expected \"type\": \"service_account\", found \"{}\"",
+                key.key_type
+            )));
+        }
+        if key.token_uri != GOOGLE_TOKEN_URI {
+            return Err(PreflightError::InvalidKey(format!(
+                "token_uri must be {GOOGLE_TOKEN_URI}, found \"{}\"",
+                key.token_uri
+            )));
+        }
+        Ok(key)
+    }
+
+    /// Hash of everything that decides which token a key gets, so a request can't be handed
+    /// the token cached for another key that merely uses the same client_email
+    fn fingerprint(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [&self.client_email, &self.private_key, &self.token_uri] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Exchange a signed JWT assertion for an OAuth access token
+    pub async fn fetch_access_token(&self, client: &reqwest::Client, scope: &str) -> Result<String, PreflightError> {
+        self.fetch_token(client, scope).await.map(|access| access.token)
+    }
+
+    /// Like fetch_access_token, keeping the expiry Google reports
+    pub async fn fetch_token(&self, client: &reqwest::Client, scope: &str) -> Result<AccessToken, PreflightError> {
+        let token_error = |reason: String| PreflightError::Token {
+            account: self.client_email.clone(),
+            reason,
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        let claims = JwtClaims {
+            iss: &self.client_email,
+            scope,
+            aud: &self.token_uri,
+            iat: now,
+            exp: now + 3600,
+        };
+        let signing_key = jsonwebtoken::EncodingKey::from_rsa_pem(self.private_key.as_bytes())
+            .map_err(|e| PreflightError::InvalidKey(format!("private_key is not a valid RSA key: {e}")))?;
+        let assertion = jsonwebtoken::encode(&jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256), &claims, &signing_key)
+            .map_err(|e| token_error(e.to_string()))?;
+
+        let response = client
+            .post(&self.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| token_error(e.to_string()))?;
+
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.map_err(|e| token_error(e.to_string()))?;
+        if !status.is_success() {
+            let reason = body["error_description"].as_str().or(body["error"].as_str()).unwrap_or("token request rejected");
+            return Err(token_error(format!("{reason} (HTTP {status})")));
+        }
+
+        let token = body["access_token"]
+            .as_str()
+            .ok_or_else(|| token_error("token response did not include access_token".to_string()))?;
+        let expires_in = body["expires_in"].as_u64().unwrap_or(3600);
+        Ok(AccessToken {
+            token: token.to_string(),
+            expires_at: Instant::now() + Duration::from_secs(expires_in),
+        })
+    }
+}
+
+/// Ask a `:testIamPermissions` endpoint which of `permissions` the caller holds and
+/// return the ones that are missing
+pub async fn missing_permissions(
+    client: &reqwest::Client,
+    resource_url: &str,
+    access_token: &str,
+    permissions: &[&str],
+) -> Result<Vec<String>, PreflightError> {
+    let response = client
+        .post(format!("{resource_url}:testIamPermissions"))
+        .bearer_auth(access_token)
+        .json(&json!({ "permissions": permissions }))
+        .send()
+        .await
+        .map_err(|e| PreflightError::Request(e.to_string()))?;
+
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let message = body["error"]["message"].as_str().unwrap_or("unexpected response");
+        return Err(PreflightError::Request(format!("{message} (HTTP {status})")));
+    }
+
+    // Google omits the field entirely when none of the permissions are granted
+    let granted: Vec<&str> = body["permissions"]
+        .as_array()
+        .map(|list| list.iter().filter_map(|p| p.as_str()).collect())
+        .unwrap_or_default();
+
+    Ok(permissions
+        .iter()
+        .filter(|p| !granted.contains(p))
+        .map(|p| p.to_string())
+        .collect())
+}
+
+/// Confirm the key can create projects under `parent` (e.g. "organizations/123")
+/// and, when given, link `billing_account` to them
+pub async fn preflight_project_creation(
+    client: &reqwest::Client,
+    key_json: &str,
+    parent: Option<&str>,
+    billing_account: Option<&str>,
+) -> Result<(), PreflightError> {
+    let key = ServiceAccountKey::from_json(key_json)?;
+    let access_token = key.fetch_access_token(client, CLOUD_PLATFORM_SCOPE).await?;
+
+    let mut checks = Vec::new();
+    if let Some(parent) = parent {
+        checks.push((parent.to_string(), format!("{RESOURCE_MANAGER_BASE_URL}/{parent}"), PROJECT_CREATE_PERMISSION));
+    }
+    if let Some(billing_account) = billing_account {
+        let resource = format!("billingAccounts/{billing_account}");
+        checks.push((resource.clone(), format!("{CLOUD_BILLING_BASE_URL}/{resource}"), BILLING_LINK_PERMISSION));
+    }
+
+    for (resource, resource_url, permission) in checks {
+        let missing = missing_permissions(client, &resource_url, &access_token, &[permission]).await?;
+        if !missing.is_empty() {
+            return Err(PreflightError::MissingPermissions {
+                account: key.client_email.clone(),
+                resource,
+                permissions: missing,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Confirm the key can read `spreadsheet_id` with a metadata-only request
+pub async fn preflight_sheets_access(
+    client: &reqwest::Client,
+    tokens: &TokenCache,
+    key_json: &str,
+    sheets_base_url: &str,
+    spreadsheet_id: &str,
+) -> Result<(), PreflightError> {
+    let key = ServiceAccountKey::from_json(key_json)?;
+    let access_token = tokens.token(client, &key, SHEETS_READONLY_SCOPE).await?;
+    check_spreadsheet_access(client, sheets_base_url, &access_token, &key.client_email, spreadsheet_id).await
+}
+
+async fn check_spreadsheet_access(
+    client: &reqwest::Client,
+    sheets_base_url: &str,
+    access_token: &str,
+    account: &str,
+    spreadsheet_id: &str,
+) -> Result<(), PreflightError> {
+    let response = client
+        .get(format!("{sheets_base_url}/spreadsheets/{spreadsheet_id}"))
+        .query(&[("fields", "spreadsheetId")])
+        .bearer_auth(access_token)
+        .send()
+        .await
+        .map_err(|e| PreflightError::Request(e.to_string()))?;
+
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::NOT_FOUND => Err(PreflightError::SpreadsheetAccess {
+            account: account.to_string(),
+            spreadsheet_id: spreadsheet_id.to_string(),
+        }),
+        status => Err(PreflightError::Request(format!("Sheets API returned HTTP {status}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_missing_permissions_reports_ungranted() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/organizations/42:testIamPermissions")
+            .match_header("authorization", "Bearer test-token")
+            .with_status(200)
+            .with_body(r#"{"permissions": ["resourcemanager.projects.get"]}"#)
+            .create_async()
+            .await;
+
+        let client = reqwest::Client::new();
+        let missing = missing_permissions(
+            &client,
+            &format!("{}/organizations/42", server.url()),
+            "test-token",
+            &["resourcemanager.projects.get", PROJECT_CREATE_PERMISSION],
+        )
+        .await
+        .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(missing, vec![PROJECT_CREATE_PERMISSION.to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_spreadsheet_forbidden_names_the_account() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/spreadsheets/sheet123")
+            .match_query(mockito::Matcher::Any)
+            .with_status(403)
+            .create_async()
+            .await;
+
+        let client = reqwest::Client::new();
+        let err = check_spreadsheet_access(&client, &server.url(), "token", "bot@example.iam.gserviceaccount.com", "sheet123")
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("bot@example.iam.gserviceaccount.com"));
+        assert!(err.to_string().contains("sheet123"));
+    }
+
+    #[tokio::test]
+    async fn test_token_cache_refreshes_near_expiry() {
+        let cache = TokenCache::default();
+        let minted = std::sync::atomic::AtomicUsize::new(0);
+        let mint = |lifetime: u64| {
+            let n = minted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                Ok(AccessToken {
+                    token: format!("token-{n}"),
+                    expires_at: Instant::now() + Duration::from_secs(lifetime),
+                })
+            }
+        };
+        let key = || ("bot@example.iam.gserviceaccount.com".to_string(), "scope".to_string());
+
+        // A token inside the refresh margin is replaced on the next request
+        assert_eq!(cache.get_or_fetch(key(), || mint(30)).await.unwrap(), "token-0");
+        assert_eq!(cache.get_or_fetch(key(), || mint(3600)).await.unwrap(), "token-1");
+        // A fresh one is reused without minting
+        assert_eq!(cache.get_or_fetch(key(), || mint(3600)).await.unwrap(), "token-1");
+        assert_eq!(minted.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_token_cache_keys_by_whole_key() {
+        let key = |private_key: &str| {
+            ServiceAccountKey::from_json(&json!({
+                "type": "service_account",
+                "client_email": "bot@example.iam.gserviceaccount.com",
+                "private_key": private_key,
+            }).to_string())
+            .unwrap()
+        };
+        assert_eq!(key("real-key").fingerprint(), key("real-key").fingerprint());
+        assert_ne!(key("real-key").fingerprint(), key("other-key").fingerprint());
+    }
+
+    #[test]
+    fn test_rejects_other_token_endpoints() {
+        for token_uri in ["http://169.254.169.254/computeMetadata/v1/", "https://attacker.example/token", "http://oauth2.googleapis.com/token"] {
+            let err = ServiceAccountKey::from_json(&json!({
+                "type": "service_account",
+                "client_email": "bot@example.iam.gserviceaccount.com",
+                "private_key": "key",
+                "token_uri": token_uri,
+            }).to_string())
+            .unwrap_err();
+            assert!(err.to_string().contains("token_uri"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_rejects_non_service_account_keys() {
+        let err = ServiceAccountKey::from_json(r#"{"type": "authorized_user", "client_email": "a", "private_key": "b"}"#)
+            .unwrap_err();
+        assert!(matches!(err, PreflightError::InvalidKey(_)));
+    }
+}
@@ -0,0 +1,310 @@
+// src/google_projects.rs
+// Creating Google Cloud projects with a service account: Resource Manager, Cloud Billing and IAM
+
+use crate::google_cloud::{self, PreflightError};
+use serde_json::json;
+use std::time::Duration;
+
+/// Operation polls before project creation is reported as timed out
+const MAX_OPERATION_POLLS: u32 = 60;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProjectError {
+    #[error(transparent)]
+    Credentials(#[from] PreflightError),
+
+    /// Google rejected a call; `body` is its error response, unchanged
+    #[error("Google returned HTTP {status} while {step}")]
+    Api {
+        step: &'static str,
+        status: u16,
+        body: serde_json::Value,
+    },
+
+    #[error("Request failed while {step}: {reason}")]
+    Request { step: &'static str, reason: String },
+
+    #[error("Project {0} was not ready before the creation operation timed out")]
+    Timeout(String),
+}
+
+/// Base URLs and polling interval, overridable for tests
+#[derive(Debug, Clone)]
+pub struct ProjectApi {
+    pub resource_manager_url: String,
+    pub billing_url: String,
+    pub poll_interval: Duration,
+}
+
+impl Default for ProjectApi {
+    fn default() -> Self {
+        Self {
+            resource_manager_url: google_cloud::RESOURCE_MANAGER_BASE_URL.to_string(),
+            billing_url: google_cloud::CLOUD_BILLING_BASE_URL.to_string(),
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// What to create and who should own it
+#[derive(Debug, Clone)]
+pub struct NewProject<'a> {
+    pub project_id: &'a str,
+    /// "organizations/123" or "folders/456"; without one the project has no parent
+    pub parent: Option<&'a str>,
+    pub billing_account: Option<&'a str>,
+    pub owner_email: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreatedProject {
+    pub project_id: String,
+    pub project_number: String,
+    pub lifecycle_state: String,
+    pub billing_linked: bool,
+}
+
+/// Send a request and return the JSON body, keeping Google's error body on failure
+async fn send(step: &'static str, request: reqwest::RequestBuilder) -> Result<serde_json::Value, ProjectError> {
+    let response = request
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| ProjectError::Request { step, reason: e.to_string() })?;
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(ProjectError::Api { step, status: status.as_u16(), body });
+    }
+    Ok(body)
+}
+
+/// Start `projects.create` and poll the long-running operation until the project exists
+async fn create_and_wait(
+    client: &reqwest::Client,
+    api: &ProjectApi,
+    access_token: &str,
+    project: &NewProject<'_>,
+) -> Result<serde_json::Value, ProjectError> {
+    let mut body = json!({ "projectId": project.project_id, "displayName": project.project_id });
+    if let Some(parent) = project.parent {
+        body["parent"] = json!(parent);
+    }
+    let mut operation = send(
+        "creating the project",
+        client.post(format!("{}/projects", api.resource_manager_url)).bearer_auth(access_token).json(&body),
+    )
+    .await?;
+
+    let mut polls = 0;
+    while !operation["done"].as_bool().unwrap_or(false) {
+        if polls >= MAX_OPERATION_POLLS {
+            return Err(ProjectError::Timeout(project.project_id.to_string()));
+        }
+        polls += 1;
+        tokio::time::sleep(api.poll_interval).await;
+
+        let name = operation["name"].as_str().unwrap_or_default().to_string();
+        operation = send(
+            "waiting for project creation",
+            client.get(format!("{}/{name}", api.resource_manager_url)).bearer_auth(access_token),
+        )
+        .await?;
+    }
+
+    // A finished operation carries either the new project or Google's error
+    if !operation["error"].is_null() {
+        let status = operation["error"]["code"].as_u64().unwrap_or(500) as u16;
+        return Err(ProjectError::Api {
+            step: "creating the project",
+            status,
+            body: json!({ "error": operation["error"] }),
+        });
+    }
+    Ok(operation["response"].clone())
+}
+
+/// Link the project to a billing account ("012345-6789AB-CDEF01" or "billingAccounts/...")
+async fn link_billing(
+    client: &reqwest::Client,
+    api: &ProjectApi,
+    access_token: &str,
+    project_id: &str,
+    billing_account: &str,
+) -> Result<(), ProjectError> {
+    let account = billing_account.trim_start_matches("billingAccounts/");
+    send(
+        "linking billing",
+        client
+            .put(format!("{}/projects/{project_id}/billingInfo", api.billing_url))
+            .bearer_auth(access_token)
+            .json(&json!({ "billingAccountName": format!("billingAccounts/{account}") })),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Add `user:{email}` to roles/owner, keeping the rest of the policy
+async fn add_owner(
+    client: &reqwest::Client,
+    api: &ProjectApi,
+    access_token: &str,
+    project_id: &str,
+    email: &str,
+) -> Result<(), ProjectError> {
+    let resource = format!("{}/projects/{project_id}", api.resource_manager_url);
+    let mut policy = send(
+        "reading the IAM policy",
+        client.post(format!("{resource}:getIamPolicy")).bearer_auth(access_token).json(&json!({})),
+    )
+    .await?;
+
+    let member = json!(format!("user:{email}"));
+    let mut bindings = policy["bindings"].as_array().cloned().unwrap_or_default();
+    match bindings.iter_mut().find(|binding| binding["role"] == "roles/owner") {
+        Some(binding) => {
+            let members = binding["members"].as_array().cloned().unwrap_or_default();
+            if !members.contains(&member) {
+                binding["members"] = json!(members.into_iter().chain([member]).collect::<Vec<_>>());
+            }
+        }
+        None => bindings.push(json!({ "role": "roles/owner", "members": [member] })),
+    }
+    policy["bindings"] = json!(bindings);
+
+    send(
+        "adding the owner",
+        client
+            .post(format!("{resource}:setIamPolicy"))
+            .bearer_auth(access_token)
+            .json(&json!({ "policy": policy })),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Create the project, wait until it is ACTIVE, attach billing when given and make
+/// `owner_email` an owner
+pub async fn create_project(
+    client: &reqwest::Client,
+    api: &ProjectApi,
+    access_token: &str,
+    project: &NewProject<'_>,
+) -> Result<CreatedProject, ProjectError> {
+    let created = create_and_wait(client, api, access_token, project).await?;
+    // v3 names projects "projects/{number}"
+    let project_number = created["name"]
+        .as_str()
+        .map(|name| name.trim_start_matches("projects/").to_string())
+        .unwrap_or_default();
+    let lifecycle_state = created["state"].as_str().unwrap_or("ACTIVE").to_string();
+
+    let billing_linked = match project.billing_account {
+        Some(account) => {
+            link_billing(client, api, access_token, project.project_id, account).await?;
+            true
+        }
+        None => false,
+    };
+    add_owner(client, api, access_token, project.project_id, project.owner_email).await?;
+
+    Ok(CreatedProject {
+        project_id: project.project_id.to_string(),
+        project_number,
+        lifecycle_state,
+        billing_linked,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api(server: &mockito::Server) -> ProjectApi {
+        ProjectApi {
+            resource_manager_url: server.url(),
+            billing_url: server.url(),
+            poll_interval: Duration::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_project_polls_links_billing_and_adds_owner() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/projects")
+            .match_body(mockito::Matcher::PartialJson(json!({ "projectId": "demo-123", "parent": "organizations/42" })))
+            .with_body(json!({ "name": "operations/cp.1", "done": false }).to_string())
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/operations/cp.1")
+            .with_body(
+                json!({
+                    "name": "operations/cp.1",
+                    "done": true,
+                    "response": { "name": "projects/987654", "projectId": "demo-123", "state": "ACTIVE" }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let billing = server
+            .mock("PUT", "/projects/demo-123/billingInfo")
+            .match_body(mockito::Matcher::Json(json!({ "billingAccountName": "billingAccounts/0123-4567" })))
+            .with_body("{}")
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/projects/demo-123:getIamPolicy")
+            .with_body(json!({ "bindings": [{ "role": "roles/owner", "members": ["serviceAccount:bot@x.iam.gserviceaccount.com"] }], "etag": "abc" }).to_string())
+            .create_async()
+            .await;
+        let owner = server
+            .mock("POST", "/projects/demo-123:setIamPolicy")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "policy": { "etag": "abc", "bindings": [{ "role": "roles/owner", "members": ["serviceAccount:bot@x.iam.gserviceaccount.com", "user:ada@example.org"] }] }
+            })))
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let project = NewProject {
+            project_id: "demo-123",
+            parent: Some("organizations/42"),
+            billing_account: Some("0123-4567"),
+            owner_email: "ada@example.org",
+        };
+        let created = create_project(&reqwest::Client::new(), &api(&server), "token", &project).await.unwrap();
+
+        assert_eq!(created, CreatedProject {
+            project_id: "demo-123".to_string(),
+            project_number: "987654".to_string(),
+            lifecycle_state: "ACTIVE".to_string(),
+            billing_linked: true,
+        });
+        billing.assert_async().await;
+        owner.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_create_project_keeps_google_error_body() {
+        let mut server = mockito::Server::new_async().await;
+        let error = json!({ "error": { "code": 409, "message": "Requested entity already exists", "status": "ALREADY_EXISTS" } });
+        server
+            .mock("POST", "/projects")
+            .with_status(409)
+            .with_body(error.to_string())
+            .create_async()
+            .await;
+
+        let project = NewProject { project_id: "taken", parent: None, billing_account: None, owner_email: "ada@example.org" };
+        match create_project(&reqwest::Client::new(), &api(&server), "token", &project).await {
+            Err(ProjectError::Api { status, body, .. }) => {
+                assert_eq!(status, 409);
+                assert_eq!(body, error);
+            }
+            other => panic!("expected an API error, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,585 @@
+// src/google_sheets.rs
+// Member rows in a Google Sheet, read and written through the Sheets REST API with a service account
+
+use crate::google_cloud::{PreflightError, ServiceAccountKey, TokenCache};
+use serde_json::json;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Read/write access to spreadsheets shared with the service account
+pub const SPREADSHEETS_SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+/// Retries after the first attempt when Google answers 429 or 503
+const MAX_RETRIES: u32 = 4;
+
+/// Registration form field ids and the sheet columns they fill (mirrors form.js)
+const FORM_FIELD_COLUMNS: &[(&str, &str)] = &[
+    ("timestamp", "Timestamp"),
+    ("name", "Name"),
+    ("handle", "Handle"),
+    ("team", "Team"),
+    ("focus", "Focus"),
+    ("un_goals", "UN Goal"),
+    ("school", "School and Degree Program"),
+    ("degree_date", "Date Degree Completed"),
+    ("opt_department", "OPT University Department"),
+    ("opt_contact", "OPT University Department,Email Phone"),
+    ("hours_per_week", "HoursPerWeek"),
+    ("location", "Your Location"),
+    ("status", "Status"),
+    ("github", "Github"),
+    ("phone", "Phone"),
+    ("start_date", "StartDate"),
+    ("end_date", "EndDate"),
+    ("website", "Your Website"),
+    ("job_title", "Job Title"),
+    ("projects", "Projects"),
+    ("todos", "ToDos"),
+    ("note", "Note"),
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SheetsError {
+    #[error(transparent)]
+    Credentials(#[from] PreflightError),
+
+    #[error("Sheets API returned HTTP {status}: {message}")]
+    Api { status: u16, message: String },
+
+    #[error("Sheets request failed: {0}")]
+    Request(String),
+
+    #[error("A member with this email already exists (row {0})")]
+    Duplicate(usize),
+}
+
+/// Which row a save wrote, and how
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SaveOutcome {
+    Updated(usize),
+    Appended(usize),
+}
+
+impl SaveOutcome {
+    pub fn row(self) -> usize {
+        match self {
+            SaveOutcome::Updated(row) | SaveOutcome::Appended(row) => row,
+        }
+    }
+}
+
+/// Where member rows live, from the googleSheets section of admin/google/form/config.json
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetLayout {
+    pub spreadsheet_id: String,
+    pub worksheet_name: String,
+    /// Header of the column lookups match on; None means the email column
+    pub key_column: Option<String>,
+    pub header_row: usize,
+    pub data_start_row: usize,
+}
+
+impl SheetLayout {
+    /// The worksheet new members are written to: the first of `worksheets`, or `worksheetName`
+    pub fn from_config(config: &serde_json::Value) -> Result<Self, String> {
+        Ok(Self::all_from_config(config)?.remove(0))
+    }
+
+    /// Every worksheet member lookups search, in order. `worksheets` entries are either a
+    /// name or `{ "name", "keyColumn", "headerRow", "dataStartRow" }`; without the list the
+    /// single `worksheetName` (and optional `keyColumn`) is used.
+    pub fn all_from_config(config: &serde_json::Value) -> Result<Vec<Self>, String> {
+        let sheets = &config["googleSheets"];
+        let spreadsheet_id = sheets["spreadsheetId"].as_str().unwrap_or_default().trim().to_string();
+        if spreadsheet_id.is_empty() || spreadsheet_id == "REPLACE_WITH_YOUR_GOOGLE_SHEET_ID" {
+            return Err("Google Sheets not configured. Please update spreadsheetId in config.json".to_string());
+        }
+
+        let entries = match sheets["worksheets"].as_array() {
+            Some(list) if !list.is_empty() => list.clone(),
+            _ => vec![json!({
+                "name": sheets["worksheetName"].as_str().unwrap_or("Members"),
+                "keyColumn": sheets["keyColumn"],
+            })],
+        };
+
+        entries
+            .iter()
+            .map(|entry| {
+                let name = entry.as_str().or_else(|| entry["name"].as_str()).unwrap_or_default().trim();
+                if name.is_empty() {
+                    return Err("Every entry in googleSheets.worksheets needs a name".to_string());
+                }
+
+                // Per-sheet rows fall back to the top-level settings
+                let header_row = entry["headerRow"].as_u64().or(sheets["headerRow"].as_u64()).unwrap_or(1).max(1) as usize;
+                let data_start_row = entry["dataStartRow"]
+                    .as_u64()
+                    .or(sheets["dataStartRow"].as_u64())
+                    .unwrap_or(header_row as u64 + 1) as usize;
+                if data_start_row <= header_row {
+                    return Err(format!("dataStartRow ({data_start_row}) must come after headerRow ({header_row}) in worksheet '{name}'"));
+                }
+
+                Ok(SheetLayout {
+                    spreadsheet_id: spreadsheet_id.clone(),
+                    worksheet_name: name.to_string(),
+                    key_column: entry["keyColumn"].as_str().map(str::trim).filter(|c| !c.is_empty()).map(str::to_string),
+                    header_row,
+                    data_start_row,
+                })
+            })
+            .collect()
+    }
+
+    /// A1 range from the header row to the last row of the sheet
+    fn table_range(&self) -> String {
+        format!("'{}'!A{}:ZZ", self.worksheet_name.replace('\'', "''"), self.header_row)
+    }
+}
+
+/// A member row: its 1-based sheet row number, values keyed by header name, and the raw cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberRow {
+    pub row: usize,
+    pub fields: serde_json::Map<String, serde_json::Value>,
+    pub cells: Vec<String>,
+}
+
+/// Access token for Sheets calls from a GOOGLE_SERVICE_KEY JSON key, reused from `tokens` while still valid
+pub async fn access_token(client: &reqwest::Client, tokens: &TokenCache, key_json: &str) -> Result<String, PreflightError> {
+    let key = ServiceAccountKey::from_json(key_json)?;
+    tokens.token(client, &key, SPREADSHEETS_SCOPE).await
+}
+
+/// Send a request, retrying with exponential backoff and jitter while Google
+/// reports rate limiting (429) or unavailability (503)
+async fn send_with_retry(
+    build: impl Fn() -> reqwest::RequestBuilder,
+) -> Result<serde_json::Value, SheetsError> {
+    let mut attempt = 0;
+    loop {
+        let response = build()
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| SheetsError::Request(e.to_string()))?;
+        let status = response.status();
+        let retryable = status == reqwest::StatusCode::TOO_MANY_REQUESTS || status == reqwest::StatusCode::SERVICE_UNAVAILABLE;
+
+        if retryable && attempt < MAX_RETRIES {
+            attempt += 1;
+            let delay = crate::outbound::backoff_delay(Duration::from_millis(500), attempt);
+            log::warn!("Sheets API returned {status}; retrying in {delay:?} (attempt {attempt} of {MAX_RETRIES})");
+            tokio::time::sleep(delay).await;
+            continue;
+        }
+
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        if !status.is_success() {
+            let message = body["error"]["message"].as_str().unwrap_or("unexpected response").to_string();
+            return Err(SheetsError::Api { status: status.as_u16(), message });
+        }
+        return Ok(body);
+    }
+}
+
+/// `.../spreadsheets/{id}/values/{range}` with the range percent-encoded as one path segment
+fn values_url(sheets_base_url: &str, layout: &SheetLayout, range: &str) -> Result<url::Url, SheetsError> {
+    let mut url = url::Url::parse(&format!("{sheets_base_url}/spreadsheets/{}/values", layout.spreadsheet_id))
+        .map_err(|e| SheetsError::Request(format!("Invalid Sheets URL: {e}")))?;
+    url.path_segments_mut()
+        .map_err(|_| SheetsError::Request("Invalid Sheets URL".to_string()))?
+        .push(range);
+    Ok(url)
+}
+
+/// Header names and data rows (as strings) of the member table
+pub async fn read_table(
+    client: &reqwest::Client,
+    sheets_base_url: &str,
+    access_token: &str,
+    layout: &SheetLayout,
+) -> Result<(Vec<String>, Vec<Vec<String>>), SheetsError> {
+    let url = values_url(sheets_base_url, layout, &layout.table_range())?;
+    let body = send_with_retry(|| {
+        client
+            .get(url.clone())
+            .query(&[("majorDimension", "ROWS"), ("valueRenderOption", "FORMATTED_VALUE")])
+            .bearer_auth(access_token)
+    })
+    .await?;
+
+    // Google omits "values" for an empty range and trailing empty cells within a row
+    let mut rows: Vec<Vec<String>> = body["values"]
+        .as_array()
+        .map(|rows| {
+            rows.iter()
+                .map(|row| {
+                    row.as_array()
+                        .map(|cells| cells.iter().map(cell_text).collect())
+                        .unwrap_or_default()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if rows.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let header = rows.remove(0);
+    let data = rows.into_iter().skip(layout.data_start_row - layout.header_row - 1).collect();
+    Ok((header, data))
+}
+
+fn cell_text(cell: &serde_json::Value) -> String {
+    match cell {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Index of the email column: a header named "Email" (any case), else one containing "email"
+pub fn email_column(header: &[String]) -> Option<usize> {
+    header
+        .iter()
+        .position(|name| name.trim().eq_ignore_ascii_case("email"))
+        .or_else(|| header.iter().position(|name| name.to_lowercase().contains("email")))
+}
+
+/// Index of the column a worksheet is keyed by: its keyColumn header (any case), else the email column
+pub fn key_column(header: &[String], layout: &SheetLayout) -> Option<usize> {
+    match &layout.key_column {
+        Some(key) => header.iter().position(|name| name.trim().eq_ignore_ascii_case(key)),
+        None => email_column(header),
+    }
+}
+
+/// First data row whose email matches (case-insensitive), mapped to header names
+pub fn find_member_row(header: &[String], rows: &[Vec<String>], layout: &SheetLayout, email: &str) -> Option<MemberRow> {
+    find_row_by_column(header, rows, layout, email_column(header)?, email)
+}
+
+fn find_row_by_column(header: &[String], rows: &[Vec<String>], layout: &SheetLayout, column: usize, value: &str) -> Option<MemberRow> {
+    let wanted = value.trim().to_lowercase();
+
+    rows.iter().enumerate().find_map(|(offset, row)| {
+        let matches = row.get(column).is_some_and(|value| value.trim().to_lowercase() == wanted);
+        matches.then(|| MemberRow {
+            row: layout.data_start_row + offset,
+            fields: header
+                .iter()
+                .enumerate()
+                .filter(|(_, name)| !name.trim().is_empty())
+                .map(|(i, name)| (name.clone(), json!(row.get(i).cloned().unwrap_or_default())))
+                .collect(),
+            cells: row.clone(),
+        })
+    })
+}
+
+/// Column for a submitted field: a known form field id, or a header name in any case
+fn column_for_field(header: &[String], key: &str) -> Option<usize> {
+    let column = FORM_FIELD_COLUMNS
+        .iter()
+        .find(|(field, _)| field.eq_ignore_ascii_case(key))
+        .map(|(_, column)| *column)
+        .unwrap_or(key);
+    header.iter().position(|name| name.trim().eq_ignore_ascii_case(column.trim()))
+}
+
+/// Cells for a member row: the existing cells (when updating) overlaid with the submitted
+/// fields and the email. Fields without a matching column are ignored.
+pub fn build_row(header: &[String], existing: Option<&[String]>, data: &HashMap<String, String>, email: &str) -> Vec<String> {
+    let mut cells: Vec<String> = existing.map(|cells| cells.to_vec()).unwrap_or_default();
+    cells.resize(header.len().max(cells.len()), String::new());
+
+    for (key, value) in data {
+        if let Some(index) = column_for_field(header, key) {
+            cells[index] = value.clone();
+        }
+    }
+    if let Some(index) = email_column(header) {
+        cells[index] = email.trim().to_string();
+    }
+    cells
+}
+
+/// Row number from an A1 range such as "'Members'!A12:W12"
+fn first_row_of_range(range: &str) -> Option<usize> {
+    let cells = range.rsplit('!').next()?;
+    let first = cells.split(':').next()?;
+    first.trim_start_matches(|c: char| c.is_ascii_alphabetic() || c == '$').parse().ok()
+}
+
+async fn update_row(
+    client: &reqwest::Client,
+    sheets_base_url: &str,
+    access_token: &str,
+    layout: &SheetLayout,
+    row: usize,
+    cells: &[String],
+) -> Result<(), SheetsError> {
+    let range = format!("'{}'!A{row}", layout.worksheet_name.replace('\'', "''"));
+    let url = values_url(sheets_base_url, layout, &range)?;
+    let body = json!({ "range": range, "majorDimension": "ROWS", "values": [cells] });
+    send_with_retry(|| {
+        client
+            .put(url.clone())
+            // RAW stores form input as typed; USER_ENTERED would evaluate "=IMPORTXML(...)" as a formula
+            .query(&[("valueInputOption", "RAW")])
+            .bearer_auth(access_token)
+            .json(&body)
+    })
+    .await?;
+    Ok(())
+}
+
+async fn append_row(
+    client: &reqwest::Client,
+    sheets_base_url: &str,
+    access_token: &str,
+    layout: &SheetLayout,
+    cells: &[String],
+) -> Result<usize, SheetsError> {
+    let url = values_url(sheets_base_url, layout, &format!("{}:append", layout.table_range()))?;
+    let body = json!({ "majorDimension": "ROWS", "values": [cells] });
+    let response = send_with_retry(|| {
+        client
+            .post(url.clone())
+            .query(&[("valueInputOption", "RAW"), ("insertDataOption", "INSERT_ROWS")])
+            .bearer_auth(access_token)
+            .json(&body)
+    })
+    .await?;
+
+    response["updates"]["updatedRange"]
+        .as_str()
+        .and_then(first_row_of_range)
+        .ok_or_else(|| SheetsError::Request("Append response did not include the updated range".to_string()))
+}
+
+/// Write a member's row. An existing row for the email is updated in place when
+/// update_existing is set, refused when duplicates are not allowed, and otherwise
+/// a new row is appended.
+#[allow(clippy::too_many_arguments)]
+pub async fn save_member(
+    client: &reqwest::Client,
+    sheets_base_url: &str,
+    access_token: &str,
+    layout: &SheetLayout,
+    email: &str,
+    data: &HashMap<String, String>,
+    update_existing: bool,
+    allow_duplicates: bool,
+) -> Result<(SaveOutcome, MemberRow), SheetsError> {
+    let (header, rows) = read_table(client, sheets_base_url, access_token, layout).await?;
+    if header.is_empty() {
+        return Err(SheetsError::Request(format!("No header row found in worksheet '{}'", layout.worksheet_name)));
+    }
+    let existing = find_member_row(&header, &rows, layout, email);
+
+    let (outcome, cells) = match existing {
+        Some(member) if update_existing => {
+            let cells = build_row(&header, Some(&member.cells), data, email);
+            update_row(client, sheets_base_url, access_token, layout, member.row, &cells).await?;
+            (SaveOutcome::Updated(member.row), cells)
+        }
+        Some(member) if !allow_duplicates => return Err(SheetsError::Duplicate(member.row)),
+        _ => {
+            let cells = build_row(&header, None, data, email);
+            let row = append_row(client, sheets_base_url, access_token, layout, &cells).await?;
+            (SaveOutcome::Appended(row), cells)
+        }
+    };
+
+    let fields = header
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| !name.trim().is_empty())
+        .map(|(i, name)| (name.clone(), json!(cells.get(i).cloned().unwrap_or_default())))
+        .collect();
+    Ok((outcome, MemberRow { row: outcome.row(), fields, cells }))
+}
+
+/// Look a member up across worksheets, matching each on its own key column. Returns the
+/// first worksheet (in config order) with a matching row.
+pub async fn find_member<'a>(
+    client: &reqwest::Client,
+    sheets_base_url: &str,
+    access_token: &str,
+    layouts: &'a [SheetLayout],
+    key: &str,
+) -> Result<Option<(&'a SheetLayout, MemberRow)>, SheetsError> {
+    for layout in layouts {
+        let (header, rows) = read_table(client, sheets_base_url, access_token, layout).await?;
+        let Some(column) = key_column(&header, layout) else {
+            log::warn!(
+                "Worksheet '{}' has no '{}' column; skipping it in member lookup",
+                layout.worksheet_name,
+                layout.key_column.as_deref().unwrap_or("Email")
+            );
+            continue;
+        };
+        if let Some(member) = find_row_by_column(&header, &rows, layout, column, key) {
+            return Ok(Some((layout, member)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> SheetLayout {
+        SheetLayout::from_config(&json!({
+            "googleSheets": { "spreadsheetId": "sheet123", "worksheetName": "Members", "headerRow": 1, "dataStartRow": 2 }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_layout_requires_a_spreadsheet() {
+        assert!(SheetLayout::from_config(&json!({ "googleSheets": { "spreadsheetId": "REPLACE_WITH_YOUR_GOOGLE_SHEET_ID" } })).is_err());
+        assert!(SheetLayout::from_config(&json!({ "googleSheets": { "spreadsheetId": "x", "headerRow": 3, "dataStartRow": 2 } })).is_err());
+        assert!(SheetLayout::from_config(&json!({ "googleSheets": { "spreadsheetId": "x", "worksheets": [{ "keyColumn": "Github" }] } })).is_err());
+        assert_eq!(layout().key_column, None);
+        assert_eq!(layout().table_range(), "'Members'!A1:ZZ");
+    }
+
+    #[tokio::test]
+    async fn test_find_member_retries_rate_limits() {
+        let mut server = mockito::Server::new_async().await;
+        let path = mockito::Matcher::Regex(r"^/spreadsheets/sheet123/values/".to_string());
+        let limited = server
+            .mock("GET", path.clone())
+            .match_query(mockito::Matcher::Any)
+            .with_status(429)
+            .expect(1)
+            .create_async()
+            .await;
+        let rows = json!({ "values": [
+            ["Name", "Team", "Email"],
+            ["Ada", "Data", "ada@example.org"],
+            ["Grace", "", "Grace@Example.org"]
+        ]});
+        let ok = server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .match_header("authorization", "Bearer token")
+            .with_body(rows.to_string())
+            .create_async()
+            .await;
+
+        let client = reqwest::Client::new();
+        let layouts = [layout()];
+        let (found_in, member) = find_member(&client, &server.url(), "token", &layouts, "grace@example.org")
+            .await
+            .unwrap()
+            .unwrap();
+        limited.assert_async().await;
+        ok.assert_async().await;
+
+        assert_eq!(found_in.worksheet_name, "Members");
+        assert_eq!(member.row, 3);
+        assert_eq!(member.fields["Name"], json!("Grace"));
+        assert_eq!(member.fields["Team"], json!(""));
+
+        let missing = find_member(&client, &server.url(), "token", &layouts, "nobody@example.org").await.unwrap();
+        assert!(missing.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_find_member_searches_worksheets_by_key_column() {
+        let config = json!({ "googleSheets": {
+            "spreadsheetId": "sheet123",
+            "headerRow": 1,
+            "worksheets": ["Members", { "name": "Alumni", "keyColumn": "Github", "headerRow": 2 }]
+        }});
+        let layouts = SheetLayout::all_from_config(&config).unwrap();
+        assert_eq!(layouts[1].key_column.as_deref(), Some("Github"));
+        assert_eq!((layouts[1].header_row, layouts[1].data_start_row), (2, 3));
+        // Writes still go to the first worksheet
+        assert_eq!(SheetLayout::from_config(&config).unwrap().worksheet_name, "Members");
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", mockito::Matcher::Regex(r"^/spreadsheets/sheet123/values/'Members'".to_string()))
+            .match_query(mockito::Matcher::Any)
+            .with_body(json!({ "values": [["Name", "Email", "Github"], ["Ada", "ada@example.org", "adal"]] }).to_string())
+            .create_async()
+            .await;
+        server
+            .mock("GET", mockito::Matcher::Regex(r"^/spreadsheets/sheet123/values/'Alumni'".to_string()))
+            .match_query(mockito::Matcher::Any)
+            .with_body(json!({ "values": [["Name", "GitHub"], ["Grace", "ghopper"]] }).to_string())
+            .create_async()
+            .await;
+
+        let client = reqwest::Client::new();
+        let (found_in, member) = find_member(&client, &server.url(), "token", &layouts, "GHopper").await.unwrap().unwrap();
+        assert_eq!(found_in.worksheet_name, "Alumni");
+        assert_eq!(member.row, 3);
+        assert_eq!(member.fields["Name"], json!("Grace"));
+
+        // Members is keyed by email, so a handle only in its Github column does not match there
+        assert!(find_member(&client, &server.url(), "token", &layouts, "adal").await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_build_row_maps_form_fields() {
+        let header: Vec<String> = ["Timestamp", "Name", "UN Goal", "Email", "Note"].iter().map(|h| h.to_string()).collect();
+        let data = HashMap::from([
+            ("name".to_string(), "Ada".to_string()),
+            ("un_goals".to_string(), "Goal 7".to_string()),
+            ("unknown_field".to_string(), "ignored".to_string()),
+        ]);
+        let existing = vec!["2024-01-01".to_string(), "Old".to_string(), String::new(), "ada@example.org".to_string(), "keep".to_string()];
+
+        assert_eq!(build_row(&header, Some(&existing), &data, "ada@example.org"), vec!["2024-01-01", "Ada", "Goal 7", "ada@example.org", "keep"]);
+        assert_eq!(build_row(&header, None, &data, "ada@example.org"), vec!["", "Ada", "Goal 7", "ada@example.org", ""]);
+        assert_eq!(first_row_of_range("'Members'!A12:W12"), Some(12));
+    }
+
+    #[tokio::test]
+    async fn test_save_member_updates_refuses_or_appends() {
+        let mut server = mockito::Server::new_async().await;
+        let rows = json!({ "values": [["Name", "Email"], ["Ada", "ada@example.org"]] });
+        server
+            .mock("GET", mockito::Matcher::Regex(r"^/spreadsheets/sheet123/values/".to_string()))
+            .match_query(mockito::Matcher::Any)
+            .with_body(rows.to_string())
+            .create_async()
+            .await;
+        let update = server
+            .mock("PUT", mockito::Matcher::Regex(r"A2$".to_string()))
+            .match_query(mockito::Matcher::UrlEncoded("valueInputOption".to_string(), "RAW".to_string()))
+            .match_body(mockito::Matcher::PartialJson(json!({ "values": [["Ada Lovelace", "ada@example.org"]] })))
+            .with_body("{}")
+            .create_async()
+            .await;
+        let append = server
+            .mock("POST", mockito::Matcher::Regex(r"append$".to_string()))
+            .match_query(mockito::Matcher::UrlEncoded("valueInputOption".to_string(), "RAW".to_string()))
+            .with_body(json!({ "updates": { "updatedRange": "'Members'!A3:B3" } }).to_string())
+            .create_async()
+            .await;
+
+        let client = reqwest::Client::new();
+        let base = server.url();
+        let renamed = HashMap::from([("name".to_string(), "Ada Lovelace".to_string())]);
+
+        let (outcome, member) = save_member(&client, &base, "token", &layout(), "ada@example.org", &renamed, true, false).await.unwrap();
+        assert_eq!(outcome, SaveOutcome::Updated(2));
+        assert_eq!(member.fields["Name"], json!("Ada Lovelace"));
+        update.assert_async().await;
+
+        let err = save_member(&client, &base, "token", &layout(), "ada@example.org", &renamed, false, false).await.unwrap_err();
+        assert!(matches!(err, SheetsError::Duplicate(2)));
+
+        let (outcome, _) = save_member(&client, &base, "token", &layout(), "grace@example.org", &renamed, false, false).await.unwrap();
+        assert_eq!(outcome, SaveOutcome::Appended(3));
+        append.assert_async().await;
+    }
+}
@@ -13,6 +13,9 @@ pub struct ImportRequest {
     pub sheet_name: Option<String>,
     pub table_name: String,
     pub column_mappings: Option<HashMap<String, String>>,
+    /// Report what the import would do without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -21,6 +24,9 @@ pub struct ImportResponse {
     pub message: String,
     pub records_processed: Option<usize>,
     pub records_inserted: Option<usize>,
+    /// Only reported by imports that update existing rows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub records_updated: Option<usize>,
     pub records_skipped: Option<usize>,
     pub duplicate_check_columns: Option<String>,
     pub errors: Vec<String>,
@@ -61,6 +67,23 @@ pub struct DataImportResponse {
     pub errors: Vec<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct CsvImportRequest {
+    /// CSV text; the first row is the header
+    pub csv: String,
+    pub table_name: String,
+    /// CSV header -> field name the importer reads (e.g. "Organization" -> "name");
+    /// unmapped headers are used as-is
+    pub column_mappings: Option<HashMap<String, String>>,
+    /// Parse and return the first `preview_rows` records without writing anything
+    #[serde(default)]
+    pub preview: bool,
+    pub preview_rows: Option<usize>,
+}
+
+/// Records shown by a CSV preview when `preview_rows` isn't given
+const DEFAULT_CSV_PREVIEW_ROWS: usize = 10;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DemocracyLabProject {
     #[serde(rename = "project_name")]
@@ -69,8 +92,24 @@ pub struct DemocracyLabProject {
     pub description: Option<String>,
     #[serde(rename = "project_url")]
     pub url: Option<String>,
+    /// DemocracyLab's own id for the project, a number or a string
+    #[serde(rename = "project_id", default)]
+    pub external_id: Option<serde_json::Value>,
+}
+
+impl DemocracyLabProject {
+    fn external_id(&self) -> Option<String> {
+        match self.external_id.as_ref()? {
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+            _ => None,
+        }
+    }
 }
 
+/// `projects.source` for rows from the DemocracyLab feed
+const DEMOCRACYLAB_SOURCE: &str = "democracylab";
+
 /// Import Excel data into the projects table
 pub async fn import_excel_data(
     pool: web::Data<std::sync::Arc<crate::ApiState>>,
@@ -79,11 +118,12 @@ pub async fn import_excel_data(
     let db = match &pool.db {
         Some(db) => db,
         None => {
-            return Ok(HttpResponse::ServiceUnavailable().json(ImportResponse {
+            return Ok(HttpResponse::build(pool.db_unavailable_status()).json(ImportResponse {
                 success: false,
-                message: "Database not available. Server started without database connection.".to_string(),
+                message: pool.db_unavailable_message().to_string(),
                 records_processed: Some(0),
                 records_inserted: Some(0),
+                records_updated: None,
                 records_skipped: Some(0),
                 duplicate_check_columns: None,
                 errors: vec!["Database connection not available".to_string()],
@@ -102,6 +142,7 @@ pub async fn import_excel_data(
                 message: format!("Failed to read Excel file at '{}': {}", req.file_path, e),
                 records_processed: None,
                 records_inserted: None,
+                records_updated: None,
                 records_skipped: None,
                 duplicate_check_columns: None,
                 errors: vec![format!("File path: {} - {}", req.file_path, e.to_string())],
@@ -109,6 +150,22 @@ pub async fn import_excel_data(
         }
     };
 
+    if req.dry_run {
+        return Ok(match excel_dry_run(db, &records).await {
+            Ok(report) => HttpResponse::Ok().json(report),
+            Err(e) => HttpResponse::InternalServerError().json(ImportResponse {
+                success: false,
+                message: format!("Dry run failed: {e}"),
+                records_processed: Some(records.len()),
+                records_inserted: None,
+                records_updated: None,
+                records_skipped: None,
+                duplicate_check_columns: Some("Name + Region + Department".to_string()),
+                errors: vec![e.to_string()],
+            }),
+        });
+    }
+
     // Process and insert records
     let mut inserted_count = 0;
     let mut skipped_count = 0;
@@ -140,17 +197,19 @@ pub async fn import_excel_data(
         message,
         records_processed: Some(total_records),
         records_inserted: Some(inserted_count),
+        records_updated: None,
         records_skipped: Some(skipped_count),
         duplicate_check_columns: Some("Name + Region + Department".to_string()),
         errors,
     }))
 }
 
-/// Preview Excel data without importing
+/// Preview Excel data without importing, with inferred column types checked against the target table
 pub async fn preview_excel_data(
+    state: web::Data<std::sync::Arc<crate::ApiState>>,
     req: web::Json<ImportRequest>,
 ) -> Result<HttpResponse> {
-    println!("Preview request - file_path: {}, sheet_name: {:?}", req.file_path, req.sheet_name);
+    log::debug!("Preview request - file_path: {}, sheet_name: {:?}", req.file_path, req.sheet_name);
     let records = match read_excel_file(&req.file_path, req.sheet_name.as_deref()) {
         Ok(data) => data,
         Err(e) => {
@@ -159,6 +218,7 @@ pub async fn preview_excel_data(
                 message: format!("Failed to read Excel file at '{}': {}", req.file_path, e),
                 records_processed: None,
                 records_inserted: None,
+                records_updated: None,
                 records_skipped: None,
                 duplicate_check_columns: None,
                 errors: vec![format!("File path: {} - {}", req.file_path, e.to_string())],
@@ -168,12 +228,26 @@ pub async fn preview_excel_data(
 
     // Return first 10 records for preview
     let preview_records: Vec<&ProjectRecord> = records.iter().take(10).collect();
+
+    // Type report: live column types when the database is up, the Excel importer's own fields otherwise
+    let target_types = match &state.db {
+        Some(db) => get_table_column_types(db, &req.table_name).await.unwrap_or_default(),
+        None => HashMap::new(),
+    };
+    let validation = match read_sheet_cells(&req.file_path, req.sheet_name.as_deref()) {
+        Ok((headers, rows)) => {
+            let report = analyze_sheet(&headers, &rows, req.column_mappings.as_ref(), &target_types);
+            serde_json::to_value(report).unwrap_or_default()
+        }
+        Err(e) => serde_json::json!({ "error": e.to_string() }),
+    };
     
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
         "message": format!("Preview of {} records (showing first 10)", records.len()),
         "total_records": records.len(),
-        "preview": preview_records
+        "preview": preview_records,
+        "validation": validation
     })))
 }
 
@@ -183,7 +257,7 @@ pub async fn get_excel_sheets(
 ) -> Result<HttpResponse> {
     let file_path = match req.get("file_path").and_then(|v| v.as_str()) {
         Some(path) => {
-            println!("Sheets request - file_path: {path}");
+            log::debug!("Sheets request - file_path: {path}");
             path
         },
         None => {
@@ -292,17 +366,263 @@ fn get_excel_sheet_names(file_path: &str) -> Result<Vec<String>, Box<dyn std::er
     Ok(workbook.sheet_names().clone())
 }
 
+/// Value type inferred from spreadsheet cells, ordered from most to least specific
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CellType {
+    Empty,
+    Boolean,
+    Integer,
+    Decimal,
+    Date,
+    Text,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ColumnReport {
+    pub header: String,
+    pub inferred_type: CellType,
+    pub mapped_to: Option<String>,
+    pub expected_type: Option<CellType>,
+    pub conflict: bool,
+    pub empty_cells: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RowIssue {
+    /// Spreadsheet row number (the header is row 1)
+    pub row: usize,
+    pub column: String,
+    pub value: String,
+    pub expected_type: CellType,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SheetValidation {
+    pub columns: Vec<ColumnReport>,
+    pub row_issues: Vec<RowIssue>,
+    pub rows_with_issues: usize,
+    pub row_issues_truncated: bool,
+}
+
+/// Most row issues listed in one preview
+const MAX_ROW_ISSUES: usize = 100;
+
+/// Field types of the built-in Excel import, keyed by lowercased header; unlisted headers import as text
+const DEFAULT_EXCEL_FIELD_TYPES: &[(&str, CellType)] = &[("committed", CellType::Decimal)];
+
+fn infer_cell_type(cell: &Data) -> CellType {
+    match cell {
+        Data::Empty => CellType::Empty,
+        Data::Bool(_) => CellType::Boolean,
+        Data::Int(_) => CellType::Integer,
+        Data::Float(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => CellType::Integer,
+        Data::Float(_) => CellType::Decimal,
+        Data::DateTime(_) | Data::DateTimeIso(_) => CellType::Date,
+        Data::String(s) => {
+            let value = s.trim();
+            if value.is_empty() {
+                CellType::Empty
+            } else if value.parse::<i64>().is_ok() {
+                CellType::Integer
+            } else if value.parse::<f64>().is_ok_and(|f| f.is_finite()) {
+                CellType::Decimal
+            } else if matches!(value.to_lowercase().as_str(), "true" | "false" | "yes" | "no") {
+                CellType::Boolean
+            } else if crate::parse_flexible_date(value).is_some() {
+                CellType::Date
+            } else {
+                CellType::Text
+            }
+        }
+        _ => CellType::Text,
+    }
+}
+
+/// Narrowest type that holds both; integers widen to decimals, any other mix is text
+fn widen_type(a: CellType, b: CellType) -> CellType {
+    match (a, b) {
+        (CellType::Empty, other) | (other, CellType::Empty) => other,
+        (a, b) if a == b => a,
+        (CellType::Integer, CellType::Decimal) | (CellType::Decimal, CellType::Integer) => CellType::Decimal,
+        _ => CellType::Text,
+    }
+}
+
+/// Whether a value of `actual` type will parse into a column of `expected` type
+fn is_compatible(actual: CellType, expected: CellType) -> bool {
+    actual == CellType::Empty
+        || actual == expected
+        || expected == CellType::Text
+        || (actual == CellType::Integer && expected == CellType::Decimal)
+}
+
+/// Map a Postgres information_schema data_type onto the spreadsheet types
+fn cell_type_for_pg(data_type: &str) -> CellType {
+    match data_type {
+        "smallint" | "integer" | "bigint" => CellType::Integer,
+        "numeric" | "real" | "double precision" | "money" => CellType::Decimal,
+        "boolean" => CellType::Boolean,
+        t if t == "date" || t.starts_with("timestamp") => CellType::Date,
+        _ => CellType::Text,
+    }
+}
+
+async fn get_table_column_types(pool: &Pool<Postgres>, table_name: &str) -> Result<HashMap<String, CellType>, sqlx::Error> {
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT column_name::text, data_type::text FROM information_schema.columns WHERE table_schema = 'public' AND table_name = $1"
+    )
+    .bind(table_name)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.into_iter().map(|(name, data_type)| (name, cell_type_for_pg(&data_type))).collect())
+}
+
+/// Header row and data rows of a sheet, as raw cells
+type SheetCells = (Vec<String>, Vec<Vec<Data>>);
+
+fn read_sheet_cells(file_path: &str, sheet_name: Option<&str>) -> Result<SheetCells, Box<dyn std::error::Error>> {
+    let mut workbook: Xlsx<_> = open_workbook(file_path)
+        .map_err(|e| format!("File not found at: {file_path} - {e}"))?;
+    let sheet_name = match sheet_name {
+        Some(name) => name.to_string(),
+        None => workbook.sheet_names().first().unwrap_or(&"Sheet1".to_string()).clone(),
+    };
+    let range = workbook.worksheet_range(&sheet_name)
+        .map_err(|e| format!("Error reading sheet: {e}"))?;
+
+    let mut rows = range.rows();
+    let headers = rows
+        .next()
+        .map(|row| row.iter().map(|cell| cell.to_string().trim().to_string()).collect())
+        .unwrap_or_default();
+    Ok((headers, rows.map(|row| row.to_vec()).collect()))
+}
+
+/// Infer each column's type and check it against the column it maps to. With explicit
+/// `column_mappings` (header -> DB column) the expected types come from `target_types`;
+/// without them, from the fields the Excel importer fills.
+fn analyze_sheet(
+    headers: &[String],
+    rows: &[Vec<Data>],
+    column_mappings: Option<&HashMap<String, String>>,
+    target_types: &HashMap<String, CellType>,
+) -> SheetValidation {
+    let mut columns = Vec::new();
+    let mut expected_types = Vec::new();
+
+    for (col_idx, header) in headers.iter().enumerate() {
+        let (mapped_to, expected_type) = match column_mappings {
+            Some(mappings) => {
+                let mapped = mappings.get(header).cloned();
+                let expected = mapped.as_ref().and_then(|column| target_types.get(column).copied());
+                (mapped, expected)
+            }
+            None => {
+                let key = header.to_lowercase();
+                let expected = DEFAULT_EXCEL_FIELD_TYPES
+                    .iter()
+                    .find(|(field, _)| *field == key)
+                    .map(|(_, field_type)| *field_type)
+                    .unwrap_or(CellType::Text);
+                (Some(key.replace(' ', "_")), Some(expected))
+            }
+        };
+
+        let cell_types: Vec<CellType> = rows.iter().map(|row| row.get(col_idx).map(infer_cell_type).unwrap_or(CellType::Empty)).collect();
+        let inferred_type = cell_types.iter().copied().fold(CellType::Empty, widen_type);
+        let conflict = expected_type.is_some_and(|expected| !is_compatible(inferred_type, expected));
+
+        columns.push(ColumnReport {
+            header: header.clone(),
+            inferred_type,
+            mapped_to,
+            expected_type,
+            conflict,
+            empty_cells: cell_types.iter().filter(|t| **t == CellType::Empty).count(),
+        });
+        expected_types.push(expected_type);
+    }
+
+    let mut row_issues = Vec::new();
+    let mut rows_with_issues = 0;
+    for (row_idx, row) in rows.iter().enumerate() {
+        let mut row_failed = false;
+        for (col_idx, cell) in row.iter().enumerate() {
+            let Some(Some(expected)) = expected_types.get(col_idx).copied() else { continue };
+            if is_compatible(infer_cell_type(cell), expected) {
+                continue;
+            }
+            row_failed = true;
+            if row_issues.len() < MAX_ROW_ISSUES {
+                row_issues.push(RowIssue {
+                    row: row_idx + 2,
+                    column: headers[col_idx].clone(),
+                    value: cell.to_string(),
+                    expected_type: expected,
+                });
+            }
+        }
+        if row_failed {
+            rows_with_issues += 1;
+        }
+    }
+
+    SheetValidation {
+        columns,
+        row_issues_truncated: rows_with_issues > 0 && row_issues.len() >= MAX_ROW_ISSUES,
+        row_issues,
+        rows_with_issues,
+    }
+}
+
 #[derive(Debug)]
 enum InsertResult {
     Inserted,
     Skipped,
 }
 
-async fn insert_project_record(
-    pool: &Pool<Postgres>,
-    record: &ProjectRecord,
-) -> Result<InsertResult, sqlx::Error> {
-    // Check for existing record based on name, region, and department
+/// Rows listed in an Excel dry-run report
+const DRY_RUN_SAMPLE_ROWS: usize = 20;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DryRunAction {
+    Insert,
+    Skip,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DryRunRow {
+    /// Position among the imported records, from 1
+    pub row: usize,
+    pub project_name: Option<String>,
+    pub region: Option<String>,
+    pub department: Option<String>,
+    pub action: DryRunAction,
+    /// A project with this name already exists even though region or department differ,
+    /// so the row would be added alongside it
+    pub existing_name: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExcelDryRun {
+    pub success: bool,
+    pub dry_run: bool,
+    pub message: String,
+    pub records_processed: usize,
+    pub would_insert: usize,
+    pub would_skip: usize,
+    /// Inserts whose name matches an existing project; the importer never updates rows,
+    /// so these are the ones to review for near-duplicates
+    pub existing_name_matches: usize,
+    pub duplicate_check_columns: String,
+    pub sample: Vec<DryRunRow>,
+}
+
+/// Whether a project with this name, region and department is already stored; the
+/// Excel import skips such rows
+async fn project_record_exists(pool: &Pool<Postgres>, record: &ProjectRecord) -> Result<bool, sqlx::Error> {
     let existing_count = sqlx::query_scalar::<_, i64>(
         r#"
         SELECT COUNT(*) FROM projects 
@@ -316,10 +636,69 @@ async fn insert_project_record(
     .bind(&record.department)
     .fetch_one(pool)
     .await?;
+    Ok(existing_count > 0)
+}
 
-    if existing_count > 0 {
+/// Work out what `import_excel_data` would do with `records` using read-only queries,
+/// including rows that repeat earlier rows of the same sheet
+async fn excel_dry_run(pool: &Pool<Postgres>, records: &[ProjectRecord]) -> Result<ExcelDryRun, sqlx::Error> {
+    let mut seen = std::collections::HashSet::new();
+    let (mut would_insert, mut would_skip, mut existing_name_matches) = (0, 0, 0);
+    let mut sample = Vec::new();
+
+    for (index, record) in records.iter().enumerate() {
+        let key = (record.project_name.clone(), record.region.clone(), record.department.clone());
+        let duplicate = !seen.insert(key) || project_record_exists(pool, record).await?;
+        let existing_name = !duplicate
+            && sqlx::query_scalar::<_, bool>("SELECT EXISTS (SELECT 1 FROM projects WHERE name = $1)")
+                .bind(&record.project_name)
+                .fetch_one(pool)
+                .await?;
+
+        if duplicate {
+            would_skip += 1;
+        } else {
+            would_insert += 1;
+        }
+        if existing_name {
+            existing_name_matches += 1;
+        }
+        if sample.len() < DRY_RUN_SAMPLE_ROWS {
+            sample.push(DryRunRow {
+                row: index + 1,
+                project_name: record.project_name.clone(),
+                region: record.region.clone(),
+                department: record.department.clone(),
+                action: if duplicate { DryRunAction::Skip } else { DryRunAction::Insert },
+                existing_name,
+            });
+        }
+    }
+
+    Ok(ExcelDryRun {
+        success: true,
+        dry_run: true,
+        message: format!(
+            "Dry run: {would_insert} of {} records would be imported, {would_skip} skipped as duplicates",
+            records.len()
+        ),
+        records_processed: records.len(),
+        would_insert,
+        would_skip,
+        existing_name_matches,
+        duplicate_check_columns: "Name + Region + Department".to_string(),
+        sample,
+    })
+}
+
+async fn insert_project_record(
+    pool: &Pool<Postgres>,
+    record: &ProjectRecord,
+) -> Result<InsertResult, sqlx::Error> {
+    // Check for existing record based on name, region, and department
+    if project_record_exists(pool, record).await? {
         // Record already exists, skip insertion
-        println!("Skipping duplicate project: {} (Region: {:?}, Department: {:?})", 
+        log::info!("Skipping duplicate project: {} (Region: {:?}, Department: {:?})", 
                  record.project_name.as_deref().unwrap_or("Unknown"),
                  record.region,
                  record.department);
@@ -399,8 +778,8 @@ async fn insert_project_record(
     .bind(&priority)
     .bind(now)
     .bind(now)
-    .bind("excel-import") // Creator identifier
-    .bind("excel-import") // Modifier identifier
+    .bind(crate::SYSTEM_USER_ID.to_string()) // Imports are recorded as the system user
+    .bind(crate::SYSTEM_USER_ID.to_string())
     .execute(pool)
     .await?;
 
@@ -415,11 +794,12 @@ pub async fn import_data(
     let db = match &pool.db {
         Some(db) => db,
         None => {
-            return Ok(HttpResponse::ServiceUnavailable().json(ImportResponse {
+            return Ok(HttpResponse::build(pool.db_unavailable_status()).json(ImportResponse {
                 success: false,
-                message: "Database not available. Server started without database connection.".to_string(),
+                message: pool.db_unavailable_message().to_string(),
                 records_processed: Some(0),
                 records_inserted: Some(0),
+                records_updated: None,
                 records_skipped: Some(0),
                 duplicate_check_columns: None,
                 errors: vec!["Database connection not available".to_string()],
@@ -427,17 +807,27 @@ pub async fn import_data(
         }
     };
     
+    log::info!("Data import request - table: {}, source: {}, records: {}", 
+        req.table_name, req.source, req.data.len());
+    
+    Ok(HttpResponse::Ok().json(import_records(db, &req.table_name, &req.data).await))
+}
+
+/// Insert field-name -> value records into `table_name` (accounts or projects), skipping
+/// duplicates; shared by the JSON and CSV imports
+async fn import_records(
+    db: &Pool<Postgres>,
+    table_name: &str,
+    records: &[HashMap<String, serde_json::Value>],
+) -> DataImportResponse {
     let mut errors = Vec::new();
     let mut imported_count = 0;
     let mut skipped_count = 0;
     let mut actual_duplicate_check_columns = None;
     
-    println!("Data import request - table: {}, source: {}, records: {}", 
-        req.table_name, req.source, req.data.len());
-    
-    match req.table_name.as_str() {
+    match table_name {
         "accounts" => {
-            for (index, record) in req.data.iter().enumerate() {
+            for (index, record) in records.iter().enumerate() {
                 match import_account_record(db, record).await {
                     Ok((InsertResult::Inserted, fields_used)) => {
                         imported_count += 1;
@@ -453,14 +843,14 @@ pub async fn import_data(
                     },
                     Err(e) => {
                         let error_msg = format!("Row {}: {}", index + 1, e);
-                        println!("Import error: {error_msg}");
+                        log::error!("Import error: {error_msg}");
                         errors.push(error_msg);
                     }
                 }
             }
         }
         "projects" => {
-            for (index, record) in req.data.iter().enumerate() {
+            for (index, record) in records.iter().enumerate() {
                 match import_project_record_from_json(db, record).await {
                     Ok((InsertResult::Inserted, fields_used)) => {
                         imported_count += 1;
@@ -476,50 +866,197 @@ pub async fn import_data(
                     },
                     Err(e) => {
                         let error_msg = format!("Row {}: {}", index + 1, e);
-                        println!("Import error: {error_msg}");
+                        log::error!("Import error: {error_msg}");
                         errors.push(error_msg);
                     }
                 }
             }
         }
         _ => {
-            errors.push(format!("Unsupported table: {}", req.table_name));
+            errors.push(format!("Unsupported table: {}", table_name));
         }
     }
     
-    let success = errors.is_empty() || (imported_count > 0 && errors.len() < req.data.len());
+    let success = errors.is_empty() || (imported_count > 0 && errors.len() < records.len());
     let message = if success {
         if errors.is_empty() {
             if skipped_count > 0 {
                 format!("Successfully imported {} records into {}, skipped {} duplicates", 
-                        imported_count, req.table_name, skipped_count)
+                        imported_count, table_name, skipped_count)
             } else {
-                format!("Successfully imported {} records into {}", imported_count, req.table_name)
+                format!("Successfully imported {} records into {}", imported_count, table_name)
             }
         } else {
             format!("Imported {} of {} records into {} with {} errors, skipped {} duplicates", 
-                imported_count, req.data.len(), req.table_name, errors.len(), skipped_count)
+                imported_count, records.len(), table_name, errors.len(), skipped_count)
         }
     } else {
-        format!("Failed to import data into {}", req.table_name)
+        format!("Failed to import data into {}", table_name)
     };
     
     let duplicate_check_columns = actual_duplicate_check_columns.or_else(|| {
-        match req.table_name.as_str() {
+        match table_name {
             "accounts" => Some("Name + Industry".to_string()),
             "projects" => Some("Name + Region + Department".to_string()),
             _ => None,
         }
     });
     
-    Ok(HttpResponse::Ok().json(DataImportResponse {
+    DataImportResponse {
         success,
         message,
         imported_count: Some(imported_count),
         skipped_count: Some(skipped_count),
         duplicate_check_columns,
         errors,
-    }))
+    }
+}
+
+
+/// Import CSV text into a table through the same record insertion as `/data`, or
+/// preview the parsed records with `preview: true`
+pub async fn import_csv_data(
+    pool: web::Data<std::sync::Arc<crate::ApiState>>,
+    req: web::Json<CsvImportRequest>,
+) -> Result<HttpResponse> {
+    if !matches!(req.table_name.as_str(), "accounts" | "projects") {
+        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "success": false,
+            "message": format!("Unsupported table: {}", req.table_name)
+        })));
+    }
+
+    let (headers, records) = match parse_csv(&req.csv).and_then(|rows| csv_records(rows, req.column_mappings.as_ref())) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("Failed to parse CSV: {e}")
+            })));
+        }
+    };
+
+    if req.preview {
+        let shown = req.preview_rows.unwrap_or(DEFAULT_CSV_PREVIEW_ROWS);
+        return Ok(HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "message": format!("Preview of {} records (showing first {})", records.len(), shown.min(records.len())),
+            "total_records": records.len(),
+            "headers": headers,
+            "preview": &records[..shown.min(records.len())]
+        })));
+    }
+
+    let db = match &pool.db {
+        Some(db) => db,
+        None => {
+            return Ok(HttpResponse::build(pool.db_unavailable_status()).json(ImportResponse {
+                success: false,
+                message: pool.db_unavailable_message().to_string(),
+                records_processed: Some(0),
+                records_inserted: Some(0),
+                records_updated: None,
+                records_skipped: Some(0),
+                duplicate_check_columns: None,
+                errors: vec!["Database connection not available".to_string()],
+            }));
+        }
+    };
+
+    log::info!("CSV import request - table: {}, records: {}", req.table_name, records.len());
+    Ok(HttpResponse::Ok().json(import_records(db, &req.table_name, &records).await))
+}
+
+/// Split CSV text into rows of fields (RFC 4180): quoted fields may hold commas, line
+/// breaks and doubled quotes; CRLF and LF line endings are both accepted
+fn parse_csv(text: &str) -> std::result::Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut quote_line = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => {
+                in_quotes = true;
+                quote_line = line;
+            }
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+                line += 1;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!("unterminated quoted field starting on line {quote_line}"));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    // Blank lines carry no record
+    rows.retain(|row| !(row.len() == 1 && row[0].trim().is_empty()));
+    Ok(rows)
+}
+
+/// Header list and one field-name -> value record per data row
+type CsvRecords = (Vec<String>, Vec<HashMap<String, serde_json::Value>>);
+
+/// Turn parsed rows into records keyed by the mapped header names; blank cells are left
+/// out so the importers' fallbacks apply
+fn csv_records(
+    rows: Vec<Vec<String>>,
+    column_mappings: Option<&HashMap<String, String>>,
+) -> std::result::Result<CsvRecords, String> {
+    let mut rows = rows.into_iter();
+    let headers: Vec<String> = rows
+        .next()
+        .ok_or("the CSV is empty")?
+        .into_iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+    let fields: Vec<String> = headers
+        .iter()
+        .map(|header| column_mappings.and_then(|m| m.get(header)).cloned().unwrap_or_else(|| header.clone()))
+        .collect();
+
+    let mut records = Vec::new();
+    for (index, row) in rows.enumerate() {
+        if row.len() > fields.len() {
+            return Err(format!("record {} has {} fields but the header has {}", index + 1, row.len(), fields.len()));
+        }
+        let record = fields
+            .iter()
+            .zip(row)
+            .filter(|(_, value)| !value.trim().is_empty())
+            .map(|(field, value)| (field.clone(), serde_json::Value::String(value.trim().to_string())))
+            .collect();
+        records.push(record);
+    }
+    Ok((headers, records))
 }
 
 /// Helper function to import a single account record
@@ -592,7 +1129,7 @@ async fn import_account_record(
         } else {
             format!("Name: {name}")
         };
-        println!("Skipping duplicate account: {fields_used}");
+        log::info!("Skipping duplicate account: {fields_used}");
         return Ok((InsertResult::Skipped, duplicate_check_fields));
     }
 
@@ -622,8 +1159,8 @@ async fn import_account_record(
     .bind(website)
     .bind(now)
     .bind(now)
-    .bind("csv-import") // Creator identifier
-    .bind("csv-import") // Modifier identifier
+    .bind(crate::SYSTEM_USER_ID.to_string()) // Imports are recorded as the system user
+    .bind(crate::SYSTEM_USER_ID.to_string())
     .execute(pool)
     .await?;
 
@@ -664,7 +1201,7 @@ async fn import_project_record_from_json(
     .await?;
 
     if existing_count > 0 {
-        println!("Skipping duplicate project: {name}");
+        log::info!("Skipping duplicate project: {name}");
         return Ok((InsertResult::Skipped, "Name".to_string()));
     }
 
@@ -685,8 +1222,8 @@ async fn import_project_record_from_json(
     .bind("Active") // Default status
     .bind(now)
     .bind(now)
-    .bind("json-import")
-    .bind("json-import")
+    .bind(crate::SYSTEM_USER_ID.to_string()) // Imports are recorded as the system user
+    .bind(crate::SYSTEM_USER_ID.to_string())
     .execute(pool)
     .await?;
 
@@ -696,6 +1233,14 @@ async fn import_project_record_from_json(
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DemocracyLabApiResponse {
     pub projects: Vec<DemocracyLabProject>,
+    /// Update rows already imported with the same DemocracyLab id instead of skipping
+    /// or duplicating them; projects without an id fall back to the name check
+    #[serde(default = "default_upsert")]
+    pub upsert: bool,
+}
+
+fn default_upsert() -> bool {
+    true
 }
 
 pub async fn import_democracylab_projects(
@@ -705,11 +1250,12 @@ pub async fn import_democracylab_projects(
     let db = match &pool.db {
         Some(db) => db,
         None => {
-            return Ok(HttpResponse::ServiceUnavailable().json(ImportResponse {
+            return Ok(HttpResponse::build(pool.db_unavailable_status()).json(ImportResponse {
                 success: false,
-                message: "Database not available. Server started without database connection.".to_string(),
+                message: pool.db_unavailable_message().to_string(),
                 records_processed: Some(0),
                 records_inserted: Some(0),
+                records_updated: None,
                 records_skipped: Some(0),
                 duplicate_check_columns: None,
                 errors: vec!["Database connection not available".to_string()],
@@ -717,43 +1263,151 @@ pub async fn import_democracylab_projects(
         }
     };
     
-    let mut errors = Vec::new();
-    let mut inserted_count = 0;
-    let mut skipped_count = 0;
     let total_records = req.projects.len();
-
-    for (index, project) in req.projects.iter().enumerate() {
-        match insert_democracylab_project(db, project).await {
-            Ok(InsertResult::Inserted) => inserted_count += 1,
-            Ok(InsertResult::Skipped) => skipped_count += 1,
-            Err(e) => {
-                errors.push(format!("Row {}: {}", index + 1, e));
-            }
-        }
-    }
+    let DemocracyLabCounts { inserted: inserted_count, updated: updated_count, skipped: skipped_count, errors } =
+        import_democracylab_feed(db, &req.projects, req.upsert).await;
 
     let message = if errors.is_empty() {
-        if skipped_count > 0 {
-            format!("Successfully imported {inserted_count} projects, skipped {skipped_count} duplicates")
-        } else {
-            format!("Successfully imported {inserted_count} projects")
-        }
+        format!("Successfully imported {inserted_count} projects, updated {updated_count}, skipped {skipped_count} duplicates")
     } else {
-        format!("Imported {} of {} projects with {} errors, skipped {} duplicates",
-                inserted_count, total_records, errors.len(), skipped_count)
+        format!("Imported {} of {} projects with {} errors, updated {}, skipped {} duplicates",
+                inserted_count, total_records, errors.len(), updated_count, skipped_count)
     };
 
     Ok(HttpResponse::Ok().json(ImportResponse {
-        success: errors.is_empty() || inserted_count > 0,
+        success: errors.is_empty() || inserted_count + updated_count > 0,
         message,
         records_processed: Some(total_records),
         records_inserted: Some(inserted_count),
+        records_updated: Some(updated_count),
         records_skipped: Some(skipped_count),
-        duplicate_check_columns: Some("Name".to_string()),
+        duplicate_check_columns: Some(if req.upsert { "DemocracyLab id, else Name" } else { "Name" }.to_string()),
         errors,
     }))
 }
 
+/// Outcome of importing one DemocracyLab feed
+#[derive(Debug, Default)]
+struct DemocracyLabCounts {
+    inserted: usize,
+    updated: usize,
+    skipped: usize,
+    errors: Vec<String>,
+}
+
+async fn import_democracylab_feed(
+    pool: &Pool<Postgres>,
+    projects: &[DemocracyLabProject],
+    upsert: bool,
+) -> DemocracyLabCounts {
+    let mut counts = DemocracyLabCounts::default();
+    for (index, project) in projects.iter().enumerate() {
+        let result = match project.external_id().filter(|_| upsert) {
+            Some(external_id) => upsert_democracylab_project(pool, project, &external_id).await,
+            None => insert_democracylab_project(pool, project).await.map(|result| match result {
+                InsertResult::Inserted => UpsertResult::Inserted,
+                InsertResult::Skipped => UpsertResult::Skipped,
+            }),
+        };
+        match result {
+            Ok(UpsertResult::Inserted) => counts.inserted += 1,
+            Ok(UpsertResult::Updated) => counts.updated += 1,
+            Ok(UpsertResult::Skipped) => counts.skipped += 1,
+            Err(e) => counts.errors.push(format!("Row {}: {}", index + 1, e)),
+        }
+    }
+    counts
+}
+
+fn democracylab_description(project: &DemocracyLabProject) -> Option<String> {
+    let mut description_parts = Vec::new();
+    if let Some(desc) = &project.description {
+        description_parts.push(desc.clone());
+    }
+    if let Some(url) = &project.url {
+        description_parts.push(format!("Project URL: {url}"));
+    }
+    if description_parts.is_empty() {
+        None
+    } else {
+        Some(description_parts.join("\n\n"))
+    }
+}
+
+enum UpsertResult {
+    Inserted,
+    Updated,
+    /// The matching row was soft-deleted and is left alone
+    Skipped,
+}
+
+/// Insert the project, or refresh the name and description of the row imported earlier
+/// with the same DemocracyLab id. A row added before sources were tracked (`source IS NULL`)
+/// with the same name is claimed for this id first, so re-importing does not duplicate it.
+async fn upsert_democracylab_project(
+    pool: &Pool<Postgres>,
+    project: &DemocracyLabProject,
+    external_id: &str,
+) -> Result<UpsertResult, sqlx::Error> {
+    let now = Utc::now();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE projects SET source = $1, external_id = $2
+        WHERE id = (
+            SELECT id FROM projects
+            WHERE source IS NULL AND name = $3
+            ORDER BY date_entered
+            LIMIT 1
+        )
+        AND NOT EXISTS (SELECT 1 FROM projects WHERE source = $1 AND external_id = $2)
+        "#
+    )
+    .bind(DEMOCRACYLAB_SOURCE)
+    .bind(external_id)
+    .bind(&project.name)
+    .execute(&mut *tx)
+    .await?;
+
+    // xmax is 0 only for a freshly inserted row version; a soft-deleted match returns no row
+    let inserted = sqlx::query_scalar::<_, bool>(
+        r#"
+        INSERT INTO projects (
+            id, name, description, status,
+            date_entered, date_modified, created_by, modified_user_id,
+            source, external_id
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (source, external_id) DO UPDATE SET
+            name = EXCLUDED.name,
+            description = EXCLUDED.description,
+            date_modified = EXCLUDED.date_modified,
+            modified_user_id = EXCLUDED.modified_user_id
+        WHERE NOT projects.deleted
+        RETURNING (xmax = 0)
+        "#
+    )
+    .bind(Uuid::new_v4())
+    .bind(&project.name)
+    .bind(democracylab_description(project))
+    .bind("Active") // Default status, kept on update
+    .bind(now)
+    .bind(now)
+    .bind(crate::SYSTEM_USER_ID.to_string()) // Imports are recorded as the system user
+    .bind(crate::SYSTEM_USER_ID.to_string())
+    .bind(DEMOCRACYLAB_SOURCE)
+    .bind(external_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(match inserted {
+        Some(true) => UpsertResult::Inserted,
+        Some(false) => UpsertResult::Updated,
+        None => UpsertResult::Skipped,
+    })
+}
+
 async fn insert_democracylab_project(
     pool: &Pool<Postgres>,
     project: &DemocracyLabProject,
@@ -770,46 +1424,224 @@ async fn insert_democracylab_project(
     .await?;
 
     if existing_count > 0 {
-        println!("Skipping duplicate project: {}", &project.name);
+        log::info!("Skipping duplicate project: {}", &project.name);
         return Ok(InsertResult::Skipped);
     }
 
     let id = Uuid::new_v4();
     let now = Utc::now();
 
-    let mut description_parts = Vec::new();
-    if let Some(desc) = &project.description {
-        description_parts.push(desc.clone());
-    }
-    if let Some(url) = &project.url {
-        description_parts.push(format!("Project URL: {url}"));
-    }
-    let description = if description_parts.is_empty() {
-        None
-    } else {
-        Some(description_parts.join("
-
-"))
-    };
-
     sqlx::query(
         r#"
         INSERT INTO projects (
             id, name, description, status,
-            date_entered, date_modified, created_by, modified_user_id
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            date_entered, date_modified, created_by, modified_user_id,
+            source, external_id
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
         "#
     )
     .bind(id)
     .bind(&project.name)
-    .bind(&description)
+    .bind(democracylab_description(project))
     .bind("Active") // Default status
     .bind(now)
     .bind(now)
-    .bind("democracylab-import")
-    .bind("democracylab-import")
+    .bind(crate::SYSTEM_USER_ID.to_string()) // Imports are recorded as the system user
+    .bind(crate::SYSTEM_USER_ID.to_string())
+    .bind(DEMOCRACYLAB_SOURCE)
+    .bind(project.external_id())
     .execute(pool)
     .await?;
 
     Ok(InsertResult::Inserted)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer_cell_types() {
+        assert_eq!(infer_cell_type(&Data::Int(4)), CellType::Integer);
+        assert_eq!(infer_cell_type(&Data::Float(2024.0)), CellType::Integer);
+        assert_eq!(infer_cell_type(&Data::Float(1.5)), CellType::Decimal);
+        assert_eq!(infer_cell_type(&Data::String(" 12.50 ".into())), CellType::Decimal);
+        assert_eq!(infer_cell_type(&Data::String("Yes".into())), CellType::Boolean);
+        assert_eq!(infer_cell_type(&Data::String("2024-05-01".into())), CellType::Date);
+        assert_eq!(infer_cell_type(&Data::String("n/a".into())), CellType::Text);
+        assert_eq!(infer_cell_type(&Data::String("  ".into())), CellType::Empty);
+        assert_eq!(widen_type(CellType::Integer, CellType::Decimal), CellType::Decimal);
+        assert_eq!(widen_type(CellType::Date, CellType::Integer), CellType::Text);
+    }
+
+    #[test]
+    fn test_parse_csv_handles_quotes_and_embedded_commas() {
+        let text = "Name,Industry,Notes\r\n\"Acme, Inc.\",Energy,\"Said \"\"hi\"\"\nthen left\"\r\n\nSolo,,\n";
+        let rows = parse_csv(text).unwrap();
+        assert_eq!(rows, vec![
+            vec!["Name", "Industry", "Notes"],
+            vec!["Acme, Inc.", "Energy", "Said \"hi\"\nthen left"],
+            vec!["Solo", "", ""],
+        ]);
+        assert!(parse_csv("Name\n\"unterminated\n").is_err());
+
+        let mappings = HashMap::from([("Industry".to_string(), "sector".to_string())]);
+        let (headers, records) = csv_records(rows, Some(&mappings)).unwrap();
+        assert_eq!(headers, vec!["Name", "Industry", "Notes"]);
+        assert_eq!(records[0]["Name"], "Acme, Inc.");
+        assert_eq!(records[0]["sector"], "Energy");
+        assert!(!records[1].contains_key("sector"));
+
+        let ragged = parse_csv("Name\na,b\n").unwrap();
+        assert!(csv_records(ragged, None).is_err());
+    }
+
+    fn project_record(name: &str, region: &str) -> ProjectRecord {
+        ProjectRecord {
+            fiscal_year: None,
+            project_number: None,
+            project_type: None,
+            region: Some(region.to_string()),
+            country: None,
+            department: Some("Energy".to_string()),
+            framework: None,
+            project_name: Some(name.to_string()),
+            committed: None,
+            naics_sector: None,
+            project_description: None,
+            project_profile_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_excel_dry_run_counts_without_writing() {
+        let Some(pool) = crate::tests::test_pool().await else { return };
+        crate::init_database(&pool).await.unwrap();
+
+        let suffix = &Uuid::new_v4().simple().to_string()[..12];
+        let (stored, fresh) = (format!("Dry run stored {suffix}"), format!("Dry run fresh {suffix}"));
+        insert_project_record(&pool, &project_record(&stored, "Africa")).await.unwrap();
+
+        let records = [
+            project_record(&stored, "Africa"),
+            project_record(&stored, "Asia"),
+            project_record(&fresh, "Asia"),
+            project_record(&fresh, "Asia"),
+        ];
+        let report = excel_dry_run(&pool, &records).await.unwrap();
+        assert_eq!((report.would_insert, report.would_skip, report.existing_name_matches), (2, 2, 1));
+        let actions: Vec<_> = report.sample.iter().map(|row| (matches!(row.action, DryRunAction::Insert), row.existing_name)).collect();
+        assert_eq!(actions, [(false, false), (true, true), (true, false), (false, false)]);
+
+        let stored_rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM projects WHERE name LIKE $1")
+            .bind(format!("Dry run % {suffix}"))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stored_rows, 1);
+        sqlx::query("DELETE FROM projects WHERE name = $1").bind(&stored).execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_democracylab_upsert_updates_instead_of_duplicating() {
+        let Some(pool) = crate::tests::test_pool().await else { return };
+        crate::init_database(&pool).await.unwrap();
+
+        let external_id = Uuid::new_v4().simple().to_string();
+        let mut project: DemocracyLabProject = serde_json::from_value(serde_json::json!({
+            "project_id": external_id,
+            "project_name": "Upsert first name",
+            "project_url": "https://www.democracylab.org/projects/1"
+        }))
+        .unwrap();
+        assert_eq!(project.external_id().as_deref(), Some(external_id.as_str()));
+
+        assert!(matches!(upsert_democracylab_project(&pool, &project, &external_id).await.unwrap(), UpsertResult::Inserted));
+        project.name = "Upsert renamed".to_string();
+        assert!(matches!(upsert_democracylab_project(&pool, &project, &external_id).await.unwrap(), UpsertResult::Updated));
+
+        let names: Vec<String> = sqlx::query_scalar("SELECT name FROM projects WHERE source = $1 AND external_id = $2")
+            .bind(DEMOCRACYLAB_SOURCE)
+            .bind(&external_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(names, ["Upsert renamed"]);
+        sqlx::query("DELETE FROM projects WHERE external_id = $1").bind(&external_id).execute(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_democracylab_feed_reimport_claims_legacy_rows_and_skips_deleted() {
+        let Some(pool) = crate::tests::test_pool().await else { return };
+        crate::init_database(&pool).await.unwrap();
+
+        let suffix = &Uuid::new_v4().simple().to_string()[..12];
+        let (legacy, fresh, deleted) = (format!("DL legacy {suffix}"), format!("DL fresh {suffix}"), format!("DL deleted {suffix}"));
+        sqlx::query("INSERT INTO projects (name, date_entered) VALUES ($1, now())").bind(&legacy).execute(&pool).await.unwrap();
+        sqlx::query("INSERT INTO projects (name, source, external_id, deleted) VALUES ($1, $2, $3, true)")
+            .bind(&deleted)
+            .bind(DEMOCRACYLAB_SOURCE)
+            .bind(format!("{suffix}-3"))
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let feed: Vec<DemocracyLabProject> = [(&legacy, 1), (&fresh, 2), (&deleted, 3)]
+            .iter()
+            .map(|(name, n)| serde_json::from_value(serde_json::json!({ "project_id": format!("{suffix}-{n}"), "project_name": name })).unwrap())
+            .collect();
+
+        let first = import_democracylab_feed(&pool, &feed, true).await;
+        assert_eq!((first.inserted, first.updated, first.skipped), (1, 1, 1), "{:?}", first.errors);
+        let second = import_democracylab_feed(&pool, &feed, true).await;
+        assert_eq!((second.inserted, second.updated, second.skipped), (0, 2, 1), "{:?}", second.errors);
+
+        let rows: Vec<(String, Option<String>)> = sqlx::query_as("SELECT name, source FROM projects WHERE name LIKE $1 ORDER BY name")
+            .bind(format!("DL % {suffix}"))
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let expected: Vec<(String, Option<String>)> = [&deleted, &fresh, &legacy]
+            .iter()
+            .map(|name| (name.to_string(), Some(DEMOCRACYLAB_SOURCE.to_string())))
+            .collect();
+        assert_eq!(rows, expected);
+        let author: String = sqlx::query_scalar("SELECT created_by FROM projects WHERE name = $1")
+            .bind(&fresh)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(author, crate::SYSTEM_USER_ID.to_string());
+        sqlx::query("DELETE FROM projects WHERE name LIKE $1").bind(format!("DL % {suffix}")).execute(&pool).await.unwrap();
+    }
+
+    #[test]
+    fn test_analyze_sheet_flags_conflicts_and_bad_rows() {
+        let headers = vec!["Project Name".to_string(), "Committed".to_string()];
+        let rows = vec![
+            vec![Data::String("Solar".into()), Data::Float(1500.25)],
+            vec![Data::String("Wind".into()), Data::String("TBD".into())],
+            vec![Data::String("Hydro".into()), Data::Empty],
+        ];
+
+        let report = analyze_sheet(&headers, &rows, None, &HashMap::new());
+        assert_eq!(report.columns[0].inferred_type, CellType::Text);
+        assert!(!report.columns[0].conflict);
+        assert_eq!(report.columns[1].mapped_to.as_deref(), Some("committed"));
+        assert_eq!(report.columns[1].inferred_type, CellType::Text);
+        assert!(report.columns[1].conflict);
+        assert_eq!(report.columns[1].empty_cells, 1);
+        assert_eq!(report.rows_with_issues, 1);
+        assert_eq!(report.row_issues[0].row, 3);
+        assert_eq!(report.row_issues[0].value, "TBD");
+
+        // Explicit mappings check against the target table's column types
+        let mappings = HashMap::from([("Committed".to_string(), "amount".to_string())]);
+        let target_types = HashMap::from([("amount".to_string(), cell_type_for_pg("numeric"))]);
+        let report = analyze_sheet(&headers, &rows[..1], Some(&mappings), &target_types);
+        assert_eq!(report.columns[0].expected_type, None);
+        assert_eq!(report.columns[1].expected_type, Some(CellType::Decimal));
+        assert!(!report.columns[1].conflict);
+        assert!(report.row_issues.is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+// src/metrics.rs
+// Process-wide counters served at /metrics in the Prometheus text exposition format
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::HttpResponse;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Default, Clone, Copy)]
+struct Summary {
+    count: u64,
+    sum_seconds: f64,
+}
+
+impl Summary {
+    fn observe(&mut self, duration: Duration) {
+        self.count += 1;
+        self.sum_seconds += duration.as_secs_f64();
+    }
+}
+
+struct Counters {
+    /// (method, route pattern, status) -> requests
+    http_requests: BTreeMap<(String, String, u16), u64>,
+    /// route pattern -> latency
+    http_duration: BTreeMap<String, Summary>,
+    /// (provider, "prompt" | "completion") -> tokens
+    ai_tokens: BTreeMap<(&'static str, &'static str), u64>,
+    /// operation -> latency
+    db_queries: BTreeMap<&'static str, Summary>,
+    /// proxy kind -> bytes returned to clients
+    proxy_bytes: BTreeMap<&'static str, u64>,
+}
+
+/// Lives in a static, like the AI provider health, so any module can record without ApiState
+pub struct Metrics {
+    counters: Mutex<Counters>,
+    in_flight: AtomicUsize,
+}
+
+pub static METRICS: Metrics = Metrics::new();
+
+impl Metrics {
+    const fn new() -> Self {
+        Metrics {
+            counters: Mutex::new(Counters {
+                http_requests: BTreeMap::new(),
+                http_duration: BTreeMap::new(),
+                ai_tokens: BTreeMap::new(),
+                db_queries: BTreeMap::new(),
+                proxy_bytes: BTreeMap::new(),
+            }),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    /// Requests that have entered the middleware and not yet produced a response
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn record_http(&self, method: &str, path: &str, status: u16, duration: Duration) {
+        let mut counters = self.counters.lock().unwrap();
+        *counters.http_requests.entry((method.to_string(), path.to_string(), status)).or_default() += 1;
+        counters.http_duration.entry(path.to_string()).or_default().observe(duration);
+    }
+
+    pub fn record_tokens(&self, provider: &'static str, prompt: Option<u32>, completion: Option<u32>) {
+        let mut counters = self.counters.lock().unwrap();
+        *counters.ai_tokens.entry((provider, "prompt")).or_default() += u64::from(prompt.unwrap_or(0));
+        *counters.ai_tokens.entry((provider, "completion")).or_default() += u64::from(completion.unwrap_or(0));
+    }
+
+    pub fn record_db_query(&self, operation: &'static str, duration: Duration) {
+        self.counters.lock().unwrap().db_queries.entry(operation).or_default().observe(duration);
+    }
+
+    pub fn record_proxy_bytes(&self, kind: &'static str, bytes: usize) {
+        *self.counters.lock().unwrap().proxy_bytes.entry(kind).or_default() += bytes as u64;
+    }
+
+    pub fn render(&self) -> String {
+        let counters = self.counters.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP http_requests_in_flight HTTP requests currently being handled.\n");
+        out.push_str("# TYPE http_requests_in_flight gauge\n");
+        let _ = writeln!(out, "http_requests_in_flight {}", self.in_flight());
+
+        out.push_str("# HELP http_requests_total HTTP requests by method, route and status.\n");
+        out.push_str("# TYPE http_requests_total counter\n");
+        for ((method, path, status), count) in &counters.http_requests {
+            let _ = writeln!(
+                out,
+                "http_requests_total{{method=\"{}\",path=\"{}\",status=\"{status}\"}} {count}",
+                escape(method),
+                escape(path)
+            );
+        }
+
+        out.push_str("# HELP http_request_duration_seconds Time spent handling HTTP requests by route.\n");
+        out.push_str("# TYPE http_request_duration_seconds summary\n");
+        for (path, summary) in &counters.http_duration {
+            write_summary(&mut out, "http_request_duration_seconds", &format!("path=\"{}\"", escape(path)), summary);
+        }
+
+        out.push_str("# HELP ai_tokens_total Tokens reported by AI providers.\n");
+        out.push_str("# TYPE ai_tokens_total counter\n");
+        for ((provider, kind), count) in &counters.ai_tokens {
+            let _ = writeln!(out, "ai_tokens_total{{provider=\"{provider}\",kind=\"{kind}\"}} {count}");
+        }
+
+        out.push_str("# HELP db_query_duration_seconds Time spent running database queries by operation.\n");
+        out.push_str("# TYPE db_query_duration_seconds summary\n");
+        for (operation, summary) in &counters.db_queries {
+            write_summary(&mut out, "db_query_duration_seconds", &format!("operation=\"{operation}\""), summary);
+        }
+
+        out.push_str("# HELP proxy_response_bytes_total Bytes relayed to clients by the proxy endpoints.\n");
+        out.push_str("# TYPE proxy_response_bytes_total counter\n");
+        for (kind, bytes) in &counters.proxy_bytes {
+            let _ = writeln!(out, "proxy_response_bytes_total{{kind=\"{kind}\"}} {bytes}");
+        }
+
+        out
+    }
+}
+
+fn write_summary(out: &mut String, name: &str, labels: &str, summary: &Summary) {
+    let _ = writeln!(out, "{name}_count{{{labels}}} {}", summary.count);
+    let _ = writeln!(out, "{name}_sum{{{labels}}} {}", summary.sum_seconds);
+}
+
+// Label values may not contain raw backslashes, quotes or newlines
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+// Decrements the in-flight gauge even when the request future is dropped
+struct InFlightGuard;
+
+impl InFlightGuard {
+    fn enter() -> Self {
+        METRICS.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        METRICS.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Standard methods keep their own label; anything else a client sends (custom verbs
+/// are accepted by the HTTP parser) is counted as "OTHER" so it can't add series.
+fn method_label(method: &actix_web::http::Method) -> &'static str {
+    match method.as_str() {
+        "GET" => "GET",
+        "POST" => "POST",
+        "PUT" => "PUT",
+        "PATCH" => "PATCH",
+        "DELETE" => "DELETE",
+        "HEAD" => "HEAD",
+        "OPTIONS" => "OPTIONS",
+        _ => "OTHER",
+    }
+}
+
+/// Middleware counting every request. Routes are labelled by their pattern
+/// ("/api/projects/{id}") so ids don't turn into one series each.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let method = method_label(req.method());
+    let in_flight = InFlightGuard::enter();
+    let result = next.call(req).await;
+    drop(in_flight);
+
+    let (path, status) = match &result {
+        Ok(response) => (
+            response.request().match_pattern().unwrap_or_else(|| "unmatched".to_string()),
+            response.status().as_u16(),
+        ),
+        Err(e) => ("unmatched".to_string(), e.as_response_error().status_code().as_u16()),
+    };
+    METRICS.record_http(method, &path, status, started.elapsed());
+    result
+}
+
+pub async fn metrics_endpoint() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4; charset=utf-8")
+        .body(METRICS.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_and_read_body, call_service, init_service, TestRequest};
+    use actix_web::{middleware::from_fn, web, App};
+
+    #[tokio::test]
+    async fn test_requests_are_counted_by_route_pattern() {
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(track_requests))
+                .route("/metrics-test/items/{id}", web::get().to(|| async { HttpResponse::Ok().finish() }))
+                .route("/metrics", web::get().to(metrics_endpoint)),
+        )
+        .await;
+        for id in ["1", "2"] {
+            call_service(&app, TestRequest::get().uri(&format!("/metrics-test/items/{id}")).to_request()).await;
+        }
+        METRICS.record_tokens("metrics-test", Some(10), Some(5));
+        METRICS.record_proxy_bytes("metrics-test", 2048);
+
+        let body = call_and_read_body(&app, TestRequest::get().uri("/metrics").to_request()).await;
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains(r#"http_requests_total{method="GET",path="/metrics-test/items/{id}",status="200"} 2"#));
+        assert!(body.contains(r#"http_request_duration_seconds_count{path="/metrics-test/items/{id}"} 2"#));
+        assert!(body.contains(r#"ai_tokens_total{provider="metrics-test",kind="prompt"} 10"#));
+        assert!(body.contains(r#"proxy_response_bytes_total{kind="metrics-test"} 2048"#));
+    }
+
+    #[test]
+    fn test_unknown_methods_share_one_label() {
+        let method = |name: &str| actix_web::http::Method::from_bytes(name.as_bytes()).unwrap();
+        assert_eq!(method_label(&method("PATCH")), "PATCH");
+        assert_eq!(method_label(&method("PROPFIND")), "OTHER");
+        assert_eq!(method_label(&method("X-RANDOM-1234")), "OTHER");
+    }
+
+    #[test]
+    fn test_label_values_are_escaped() {
+        assert_eq!(escape("a\"b\\c\nd"), r#"a\"b\\c\nd"#);
+    }
+}
@@ -3,6 +3,11 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
+
+/// Upper bound on each token or userinfo call, so a stalled provider fails the sign-in
+/// instead of holding the callback request open
+const PROVIDER_REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct OAuthConfig {
@@ -39,6 +44,168 @@ pub struct OAuthProvider {
     pub demo_user: Option<DemoUser>,
 }
 
+impl OAuthProvider {
+    pub fn is_configured(&self) -> bool {
+        !crate::is_placeholder_value(&self.client_id) && !crate::is_placeholder_value(&self.client_secret)
+    }
+
+    /// Exchange an authorization code for an access token at the provider's token endpoint
+    pub async fn exchange_code(&self, client: &reqwest::Client, code: &str, redirect_uri: &str) -> anyhow::Result<String> {
+        let response = client
+            .post(&self.token_endpoint)
+            // GitHub answers form-encoded unless JSON is requested
+            .header("Accept", "application/json")
+            .timeout(PROVIDER_REQUEST_TIMEOUT)
+            .form(&[
+                ("grant_type", self.grant_type.as_str()),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("client_id", self.client_id.as_str()),
+                ("client_secret", self.client_secret.as_str()),
+            ])
+            .send()
+            .await
+            .with_context(|| format!("{} token request failed", self.name))?;
+
+        let status = response.status();
+        let body: serde_json::Value = response.json().await.unwrap_or_default();
+        // Some providers report errors with a 200 status, so check the body too
+        if let Some(error) = body["error"].as_str().filter(|_| body["access_token"].is_null()) {
+            let description = body["error_description"].as_str().unwrap_or(error);
+            anyhow::bail!("{} rejected the authorization code: {description}", self.name);
+        }
+        if !status.is_success() {
+            anyhow::bail!("{} token endpoint returned HTTP {status}", self.name);
+        }
+        body["access_token"]
+            .as_str()
+            .map(|token| token.to_string())
+            .ok_or_else(|| anyhow::anyhow!("{} token response did not include access_token", self.name))
+    }
+
+    /// Fetch the signed-in user from the provider's userinfo endpoint
+    pub async fn fetch_user(&self, client: &reqwest::Client, provider_name: &str, access_token: &str) -> anyhow::Result<AuthenticatedUser> {
+        let mut request = client
+            .get(&self.userinfo_endpoint)
+            .bearer_auth(access_token)
+            .header("Accept", "application/json")
+            // GitHub rejects requests without a User-Agent
+            .header("User-Agent", "partner-tools")
+            .timeout(PROVIDER_REQUEST_TIMEOUT);
+        if let Some(fields) = &self.fields {
+            request = request.query(&[("fields", fields)]);
+        }
+
+        let response = request.send().await.with_context(|| format!("{} userinfo request failed", self.name))?;
+        if !response.status().is_success() {
+            anyhow::bail!("{} userinfo endpoint returned HTTP {}", self.name, response.status());
+        }
+        let body: serde_json::Value = response.json().await.with_context(|| format!("{} userinfo was not JSON", self.name))?;
+        AuthenticatedUser::from_userinfo(provider_name, body)
+    }
+}
+
+/// Profile fields pulled from a provider's userinfo response
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthenticatedUser {
+    pub id: String,
+    pub email: String,
+    pub name: String,
+    pub picture: Option<String>,
+}
+
+impl AuthenticatedUser {
+    pub fn from_userinfo(provider_name: &str, body: serde_json::Value) -> anyhow::Result<Self> {
+        let user = match provider_name {
+            "google" => {
+                let info: GoogleUserInfo = serde_json::from_value(body)?;
+                AuthenticatedUser { id: info.id, email: info.email, name: info.name, picture: info.picture }
+            }
+            "github" => {
+                let info: GitHubUserInfo = serde_json::from_value(body)?;
+                AuthenticatedUser {
+                    id: info.id.to_string(),
+                    // Users with a private email get none back from /user
+                    email: info.email.unwrap_or_default(),
+                    name: info.name.unwrap_or_else(|| info.login.clone()),
+                    picture: info.avatar_url,
+                }
+            }
+            "linkedin" => {
+                let info: LinkedInUserInfo = serde_json::from_value(body)?;
+                let name = [info.first_name, info.last_name].into_iter().flatten().collect::<Vec<_>>().join(" ");
+                AuthenticatedUser {
+                    id: info.id,
+                    email: String::new(),
+                    name,
+                    picture: info.profile_picture.map(|p| p.display_image),
+                }
+            }
+            "microsoft" => {
+                let info: MicrosoftUserInfo = serde_json::from_value(body)?;
+                AuthenticatedUser { id: info.id, email: info.mail.unwrap_or(info.email), name: info.display_name, picture: None }
+            }
+            "facebook" => {
+                let info: FacebookUserInfo = serde_json::from_value(body)?;
+                AuthenticatedUser {
+                    id: info.id,
+                    email: info.email.unwrap_or_default(),
+                    name: info.name,
+                    picture: info.picture.map(|p| p.data.url),
+                }
+            }
+            "discord" => {
+                let info: DiscordUserInfo = serde_json::from_value(body)?;
+                AuthenticatedUser {
+                    name: info.get_display_name(),
+                    picture: info.get_avatar_url(),
+                    email: info.email.unwrap_or_default(),
+                    id: info.id,
+                }
+            }
+            // OpenID Connect standard claims
+            _ => AuthenticatedUser {
+                id: body["sub"].as_str().or(body["id"].as_str()).unwrap_or_default().to_string(),
+                email: body["email"].as_str().unwrap_or_default().to_string(),
+                name: body["name"].as_str().unwrap_or_default().to_string(),
+                picture: body["picture"].as_str().map(|p| p.to_string()),
+            },
+        };
+        if user.id.is_empty() {
+            anyhow::bail!("userinfo response did not include a user id");
+        }
+        Ok(user)
+    }
+}
+
+/// Cookie binding an issued `state` to the browser that asked for the authorize URL
+pub const STATE_COOKIE: &str = "pt_oauth_state";
+
+/// Short-lived HttpOnly cookie carrying the provider and `state` handed out by the authorize
+/// URL endpoint. The callback checks the returned `state` against it, so any instance can
+/// verify a sign-in without server-side state.
+pub fn state_cookie(provider_name: &str, state: &str, ttl: Duration) -> actix_web::cookie::Cookie<'static> {
+    actix_web::cookie::Cookie::build(STATE_COOKIE, format!("{provider_name}.{state}"))
+        .path("/api/auth")
+        .http_only(true)
+        .same_site(actix_web::cookie::SameSite::Lax)
+        .max_age(actix_web::cookie::time::Duration::seconds(ttl.as_secs() as i64))
+        .finish()
+}
+
+/// Expired copy of the state cookie, sent with the callback response so a state is single use
+pub fn cleared_state_cookie() -> actix_web::cookie::Cookie<'static> {
+    state_cookie("", "", Duration::ZERO)
+}
+
+/// True if the state cookie was issued for `provider_name` and holds `state`
+pub fn state_matches(cookie_value: &str, provider_name: &str, state: &str) -> bool {
+    match cookie_value.split_once('.') {
+        Some((provider, expected)) => provider == provider_name && !expected.is_empty() && expected == state,
+        None => false,
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct DemoUser {
     pub id: String,
@@ -64,6 +231,9 @@ pub struct UserSession {
     pub provider: String,
     pub created_at: i64,
     pub expires_at: i64,
+    /// The provider's OAuth access token, for calling its APIs on the user's behalf
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub access_token: Option<String>,
 }
 
 impl UserSession {
@@ -90,8 +260,14 @@ impl UserSession {
             provider,
             created_at: now,
             expires_at,
+            access_token: None,
         }
     }
+
+    /// The session as returned to the browser, without the provider token
+    pub fn public(&self) -> Self {
+        Self { access_token: None, ..self.clone() }
+    }
 }
 
 // Provider-specific user info structures
@@ -209,6 +385,91 @@ impl OAuthConfig {
     pub fn get_redirect_uri(&self, provider_name: &str) -> String {
         self.oauth.common.default_redirect_uri.replace("{provider}", provider_name)
     }
+    
+    // Names of providers whose client credentials are filled in, sorted for stable output
+    pub fn ready_providers(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.oauth.providers.iter()
+            .filter(|(_, provider)| provider.is_configured())
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
 }
 
-use anyhow::Context;
\ No newline at end of file
+use anyhow::Context;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn github_provider(base_url: &str) -> OAuthProvider {
+        OAuthProvider {
+            name: "GitHub".to_string(),
+            client_id: "client-id".to_string(),
+            client_secret: "client-secret".to_string(),
+            authorization_endpoint: format!("{base_url}/authorize"),
+            token_endpoint: format!("{base_url}/token"),
+            userinfo_endpoint: format!("{base_url}/user"),
+            issuer: None,
+            scopes: vec!["read:user".to_string()],
+            pkce_enabled: false,
+            response_type: "code".to_string(),
+            grant_type: "authorization_code".to_string(),
+            fields: None,
+            demo_user: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_code_exchange_and_userinfo() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/token")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("code".into(), "good-code".into()),
+                mockito::Matcher::UrlEncoded("client_secret".into(), "client-secret".into()),
+            ]))
+            .with_body(r#"{"access_token": "gho_token", "token_type": "bearer"}"#)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/token")
+            .match_body(mockito::Matcher::UrlEncoded("code".into(), "used-code".into()))
+            .with_body(r#"{"error": "bad_verification_code", "error_description": "The code is incorrect or expired."}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/user")
+            .match_header("authorization", "Bearer gho_token")
+            .with_body(r#"{"id": 583231, "login": "octocat", "name": null, "email": "octo@example.org", "avatar_url": "https://avatars.example/u/1"}"#)
+            .create_async()
+            .await;
+
+        let provider = github_provider(&server.url());
+        let client = reqwest::Client::new();
+        let token = provider.exchange_code(&client, "good-code", "http://localhost/cb").await.unwrap();
+        let user = provider.fetch_user(&client, "github", &token).await.unwrap();
+        assert_eq!(user, AuthenticatedUser {
+            id: "583231".to_string(),
+            email: "octo@example.org".to_string(),
+            name: "octocat".to_string(),
+            picture: Some("https://avatars.example/u/1".to_string()),
+        });
+
+        let err = provider.exchange_code(&client, "used-code", "http://localhost/cb").await.unwrap_err();
+        assert!(err.to_string().contains("incorrect or expired"));
+    }
+
+    #[test]
+    fn test_state_must_match_cookie_for_provider() {
+        let cookie = state_cookie("github", "abc123", Duration::from_secs(600));
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.path(), Some("/api/auth"));
+
+        assert!(state_matches(cookie.value(), "github", "abc123"));
+        assert!(!state_matches(cookie.value(), "google", "abc123"));
+        assert!(!state_matches(cookie.value(), "github", "forged"));
+        assert!(!state_matches(cleared_state_cookie().value(), "", ""));
+        assert!(!state_matches("", "github", ""));
+    }
+}
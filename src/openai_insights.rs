@@ -0,0 +1,117 @@
+// src/openai_insights.rs
+// OpenAI Chat Completions, used as a cheaper semantic search provider
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const OPENAI_MODEL: &str = "gpt-4o-mini";
+
+pub const API_KEY_SETUP_HINT: &str = "Create a key at https://platform.openai.com/api-keys and set OPENAI_API_KEY in your .env file";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenUsage {
+    pub prompt_tokens: Option<u32>,
+    pub completion_tokens: Option<u32>,
+    pub total_tokens: Option<u32>,
+}
+
+// True when OPENAI_API_KEY holds something other than a blank or placeholder value
+pub fn is_api_key_configured(api_key: &str) -> bool {
+    !crate::is_placeholder_value(api_key)
+}
+
+// Ask for a JSON object reply to `prompt`; returns the message content and token usage
+pub async fn call_openai_json(client: &reqwest::Client, api_key: &str, prompt: &str) -> anyhow::Result<(String, Option<TokenUsage>)> {
+    let result = request_openai_at(client, OPENAI_BASE_URL, api_key, prompt).await;
+    if let Ok((_, Some(usage))) = &result {
+        crate::metrics::METRICS.record_tokens("openai", usage.prompt_tokens, usage.completion_tokens);
+    }
+    result
+}
+
+async fn request_openai_at(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_key: &str,
+    prompt: &str,
+) -> anyhow::Result<(String, Option<TokenUsage>)> {
+    let response = client
+        .post(format!("{base_url}/chat/completions"))
+        .bearer_auth(api_key.trim())
+        .json(&json!({
+            "model": OPENAI_MODEL,
+            "messages": [{ "role": "user", "content": prompt }],
+            "response_format": { "type": "json_object" },
+        }))
+        .timeout(std::time::Duration::from_secs(60))
+        .send()
+        .await
+        .context("Failed to make request to OpenAI API")?;
+
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.context("Failed to parse OpenAI API response")?;
+    if !status.is_success() {
+        let message = body["error"]["message"].as_str().unwrap_or("No details");
+        anyhow::bail!("OpenAI API error {status}: {message}");
+    }
+
+    let choice = &body["choices"][0];
+    if choice["finish_reason"] == "length" {
+        anyhow::bail!("OpenAI stopped at the output token limit before finishing the JSON reply");
+    }
+    let content = choice["message"]["content"]
+        .as_str()
+        .ok_or_else(|| anyhow::anyhow!("Invalid OpenAI API response format. Response: {body}"))?;
+
+    let usage = &body["usage"];
+    let token_usage = usage.is_object().then(|| TokenUsage {
+        prompt_tokens: usage["prompt_tokens"].as_u64().map(|v| v as u32),
+        completion_tokens: usage["completion_tokens"].as_u64().map(|v| v as u32),
+        total_tokens: usage["total_tokens"].as_u64().map(|v| v as u32),
+    });
+
+    Ok((content.to_string(), token_usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_json_object_and_reads_usage() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/chat/completions")
+            .match_header("authorization", "Bearer sk-test")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "model": "gpt-4o-mini",
+                "response_format": { "type": "json_object" }
+            })))
+            .with_body(
+                json!({
+                    "choices": [{ "message": { "content": "{\"matches\":[]}" }, "finish_reason": "stop" }],
+                    "usage": { "prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15 }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let (content, usage) = request_openai_at(&reqwest::Client::new(), &server.url(), "sk-test", "find things").await.unwrap();
+        assert_eq!(content, "{\"matches\":[]}");
+        assert_eq!(usage.unwrap().total_tokens, Some(15));
+        mock.assert_async().await;
+
+        server.reset();
+        server
+            .mock("POST", "/chat/completions")
+            .with_status(401)
+            .with_body(json!({ "error": { "message": "Incorrect API key provided" } }).to_string())
+            .create_async()
+            .await;
+        let err = request_openai_at(&reqwest::Client::new(), &server.url(), "sk-bad", "find things").await.unwrap_err();
+        assert!(err.to_string().contains("Incorrect API key provided"));
+    }
+}
@@ -0,0 +1,140 @@
+// src/outbound.rs
+// One place to build the HTTP client used for calls to external services, and to pace their retries
+
+use reqwest::tls::Version;
+use std::time::Duration;
+
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+/// Generous enough for slow AI responses; this bounds the gap between reads, not the whole request
+const DEFAULT_READ_TIMEOUT_SECS: u64 = 120;
+
+/// TLS and timeout settings for outbound calls, read from the environment
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboundSettings {
+    /// OUTBOUND_MIN_TLS_VERSION: "1.2" (default) or "1.3"
+    pub min_tls_version: Version,
+    /// OUTBOUND_CONNECT_TIMEOUT_SECS
+    pub connect_timeout: Duration,
+    /// OUTBOUND_READ_TIMEOUT_SECS
+    pub read_timeout: Duration,
+}
+
+/// Parse a minimum TLS version; anything below 1.2 is refused rather than silently accepted
+pub fn parse_tls_version(value: &str) -> Result<Version, String> {
+    let normalized = value.trim().to_lowercase();
+    match normalized.trim_start_matches("tls").trim_start_matches('v').trim() {
+        "1.2" | "12" => Ok(Version::TLS_1_2),
+        "1.3" | "13" => Ok(Version::TLS_1_3),
+        other => Err(format!("Unsupported minimum TLS version '{other}' (use 1.2 or 1.3)")),
+    }
+}
+
+fn env_secs(name: &str, default: u64) -> Duration {
+    let secs = std::env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(default);
+    Duration::from_secs(secs)
+}
+
+impl OutboundSettings {
+    pub fn from_env() -> Self {
+        let min_tls_version = match std::env::var("OUTBOUND_MIN_TLS_VERSION") {
+            Ok(value) => parse_tls_version(&value).unwrap_or_else(|e| {
+                log::warn!("{e}; falling back to TLS 1.2");
+                Version::TLS_1_2
+            }),
+            Err(_) => Version::TLS_1_2,
+        };
+        OutboundSettings {
+            min_tls_version,
+            connect_timeout: env_secs("OUTBOUND_CONNECT_TIMEOUT_SECS", DEFAULT_CONNECT_TIMEOUT_SECS),
+            read_timeout: env_secs("OUTBOUND_READ_TIMEOUT_SECS", DEFAULT_READ_TIMEOUT_SECS),
+        }
+    }
+
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        // rustls, because the platform native-tls backend refuses TLS 1.3 as a minimum.
+        // Decode compressed bodies (e.g. gzip'd Google Sheets exports) transparently.
+        reqwest::Client::builder()
+            .use_rustls_tls()
+            .gzip(true)
+            .deflate(true)
+            .brotli(true)
+            .min_tls_version(self.min_tls_version)
+            .connect_timeout(self.connect_timeout)
+            .read_timeout(self.read_timeout)
+    }
+}
+
+/// Build the outbound client from the OUTBOUND_* settings. Called once at startup for
+/// ApiState::http_client, so bad settings fail the launch instead of each request.
+pub fn build_client() -> reqwest::Result<reqwest::Client> {
+    OutboundSettings::from_env().client_builder().build()
+}
+
+/// Delay before retry `attempt` (1-based): `base` doubled per earlier retry, plus up to
+/// 250ms of jitter so clients that failed together don't retry in lockstep
+pub fn backoff_delay(base: Duration, attempt: u32) -> Duration {
+    use rand::Rng;
+
+    let jitter = rand::thread_rng().gen_range(0..250);
+    base * 2u32.pow(attempt.saturating_sub(1)) + Duration::from_millis(jitter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tls_version() {
+        assert_eq!(parse_tls_version("1.2"), Ok(Version::TLS_1_2));
+        assert_eq!(parse_tls_version("TLSv1.3"), Ok(Version::TLS_1_3));
+        assert_eq!(parse_tls_version(" tls1.2 "), Ok(Version::TLS_1_2));
+        assert!(parse_tls_version("1.0").is_err());
+        assert!(parse_tls_version("1.1").is_err());
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_with_jitter() {
+        let base = Duration::from_millis(500);
+        for (attempt, floor) in [(1, 500), (2, 1000), (3, 2000)] {
+            let delay = backoff_delay(base, attempt);
+            assert!(delay >= Duration::from_millis(floor) && delay < Duration::from_millis(floor + 250), "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn test_tls_1_3_minimum_builds() {
+        let settings = OutboundSettings {
+            min_tls_version: Version::TLS_1_3,
+            connect_timeout: Duration::from_secs(1),
+            read_timeout: Duration::from_secs(1),
+        };
+        assert!(settings.client_builder().build().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_gzip_bodies_are_decoded() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let csv = "name,amount\nAcme,12.50\n";
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(csv.as_bytes()).unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/export.csv")
+            .with_header("content-type", "text/csv")
+            .with_header("content-encoding", "gzip")
+            .with_body(encoder.finish().unwrap())
+            .create_async()
+            .await;
+
+        let client = OutboundSettings::from_env().client_builder().build().unwrap();
+        let body = client.get(format!("{}/export.csv", server.url())).send().await.unwrap().text().await.unwrap();
+        assert_eq!(body, csv);
+    }
+}
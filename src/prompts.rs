@@ -20,6 +20,21 @@ pub struct ProjectData {
     pub url: Option<String>,
 }
 
+/// Reject a prompt longer than `max_chars` (MAX_PROMPT_CHARS) before it is sent to a provider
+pub fn check_prompt_size(prompt: &str, max_chars: usize) -> Result<(), String> {
+    let chars = prompt.chars().count();
+    if chars > max_chars {
+        Err(format!("Prompt is {chars} characters, over the limit of {max_chars} (MAX_PROMPT_CHARS)"))
+    } else {
+        Ok(())
+    }
+}
+
+/// True once a prompt uses 90% or more of the allowed size
+pub fn is_near_prompt_limit(prompt: &str, max_chars: usize) -> bool {
+    prompt.chars().count().saturating_mul(10) >= max_chars.saturating_mul(9)
+}
+
 /// Builds the semantic search prompt for AI analysis
 /// # Arguments
 /// * `query` - The user's search query
@@ -139,4 +154,14 @@ mod tests {
         assert!(prompt.contains("Dataset Context"));
         assert!(prompt.contains("record_count"));
     }
+
+    #[test]
+    fn test_prompt_size_limit_counts_characters() {
+        assert!(check_prompt_size("héllo", 5).is_ok());
+        let err = check_prompt_size("héllo!", 5).unwrap_err();
+        assert!(err.contains("6 characters") && err.contains("limit of 5"), "{err}");
+
+        assert!(!is_near_prompt_limit(&"a".repeat(89), 100));
+        assert!(is_near_prompt_limit(&"a".repeat(90), 100));
+    }
 }
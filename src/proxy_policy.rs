@@ -0,0 +1,269 @@
+// src/proxy_policy.rs
+// Which destinations the generic proxy endpoint may fetch, to keep it from being used for SSRF
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use url::Url;
+
+/// Redirect hops a proxy client follows, each one re-checked against the policy
+const MAX_REDIRECTS: usize = 5;
+
+/// Why a proxy destination was refused
+#[derive(Debug, PartialEq)]
+pub enum ProxyDenied {
+    /// Not an absolute http(s) URL with a host
+    InvalidUrl(String),
+    /// Host is not covered by PROXY_ALLOWED_HOSTS
+    HostNotAllowed(String),
+    /// Host is, or resolves to, a loopback, private or link-local address
+    PrivateAddress(String),
+}
+
+impl std::fmt::Display for ProxyDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProxyDenied::InvalidUrl(reason) => write!(f, "Invalid URL: {reason}"),
+            ProxyDenied::HostNotAllowed(host) => write!(f, "Host '{host}' is not in PROXY_ALLOWED_HOSTS"),
+            ProxyDenied::PrivateAddress(host) => write!(f, "Host '{host}' resolves to a private or loopback address"),
+        }
+    }
+}
+
+impl std::error::Error for ProxyDenied {}
+
+/// Split PROXY_ALLOWED_HOSTS into lowercase entries, ignoring blanks
+pub fn parse_allowed_hosts(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|entry| entry.trim().trim_start_matches("*.").trim_start_matches('.').to_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+/// An entry matches the host itself and any subdomain of it. An empty list allows every host.
+pub fn host_allowed(host: &str, allowed_hosts: &[String]) -> bool {
+    if allowed_hosts.is_empty() {
+        return true;
+    }
+    let host = host.trim_end_matches('.').to_lowercase();
+    allowed_hosts
+        .iter()
+        .any(|entry| host == *entry || host.ends_with(&format!(".{entry}")))
+}
+
+/// Loopback, private, link-local (including cloud metadata), CGNAT, benchmarking, multicast
+/// and unspecified addresses. IPv6 forms that carry an IPv4 address are judged by that address.
+pub fn is_private_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_private_v4(v4),
+        IpAddr::V6(v6) => match embedded_v4(v6) {
+            Some(v4) => is_private_v4(v4),
+            None => is_private_v6(v6),
+        },
+    }
+}
+
+/// The IPv4 address inside IPv4-mapped (::ffff:a.b.c.d), IPv4-compatible (::a.b.c.d),
+/// NAT64 (64:ff9b::/96) and 6to4 (2002::/16) addresses, which can all route to it
+fn embedded_v4(ip: Ipv6Addr) -> Option<Ipv4Addr> {
+    let segments = ip.segments();
+    let v4 = |high: u16, low: u16| Ipv4Addr::from((u32::from(high) << 16) | u32::from(low));
+    match segments {
+        // ::1 and :: are IPv6 loopback and unspecified, not IPv4-compatible addresses
+        _ if ip.is_loopback() || ip.is_unspecified() => None,
+        [0, 0, 0, 0, 0, 0 | 0xffff, high, low] => Some(v4(high, low)),
+        [0x64, 0xff9b, 0, 0, 0, 0, high, low] => Some(v4(high, low)),
+        [0x2002, high, low, ..] => Some(v4(high, low)),
+        _ => None,
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || a == 0
+        // 100.64.0.0/10 carrier-grade NAT
+        || (a == 100 && (64..128).contains(&b))
+        // 198.18.0.0/15 benchmarking
+        || (a == 198 && (b & 0xfe) == 18)
+}
+
+fn is_private_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // fc00::/7 unique local
+        || (first & 0xfe00) == 0xfc00
+        // fe80::/10 link-local
+        || (first & 0xffc0) == 0xfe80
+}
+
+/// The checks that need no DNS lookup: scheme, host allowlist, and literal internal IPs.
+/// Returns the bare host name.
+fn check_url(parsed: &Url, allowed_hosts: &[String], allow_private: bool) -> Result<String, ProxyDenied> {
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(ProxyDenied::InvalidUrl("must be HTTP or HTTPS".to_string()));
+    }
+    let Some(host) = parsed.host_str() else {
+        return Err(ProxyDenied::InvalidUrl("missing host".to_string()));
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_string();
+
+    if !host_allowed(&host, allowed_hosts) {
+        return Err(ProxyDenied::HostNotAllowed(host));
+    }
+    if !allow_private {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            if is_private_address(ip) {
+                return Err(ProxyDenied::PrivateAddress(host));
+            }
+        }
+    }
+    Ok(host)
+}
+
+/// Check a proxy destination against the allowlist and, unless allow_private is set,
+/// refuse hosts that resolve to internal addresses. Fetch it with [`guarded_client`] so
+/// redirects and the address actually connected to are held to the same rules.
+pub async fn check_destination(url: &str, allowed_hosts: &[String], allow_private: bool) -> Result<(), ProxyDenied> {
+    let parsed = Url::parse(url).map_err(|e| ProxyDenied::InvalidUrl(e.to_string()))?;
+    let host = check_url(&parsed, allowed_hosts, allow_private)?;
+    if allow_private || host.parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let private = match tokio::net::lookup_host((host.as_str(), port)).await {
+        Ok(mut resolved) => resolved.any(|addr| is_private_address(addr.ip())),
+        // Let the fetch itself report hosts that don't resolve
+        Err(_) => false,
+    };
+    if private {
+        return Err(ProxyDenied::PrivateAddress(host));
+    }
+    Ok(())
+}
+
+/// Resolves names for proxy clients and refuses any that point at an internal address.
+/// reqwest connects to exactly the addresses returned here, so a DNS answer that changes
+/// after check_destination (rebinding) is caught at connect time.
+struct PublicOnlyResolver;
+
+impl reqwest::dns::Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if addresses.iter().any(|addr| is_private_address(addr.ip())) {
+                return Err(Box::new(ProxyDenied::PrivateAddress(host)) as Box<dyn std::error::Error + Send + Sync>);
+            }
+            Ok(Box::new(addresses.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// An outbound client for fetching user-supplied URLs. Every redirect hop is re-checked
+/// against the allowlist and, unless allow_private is set, names are resolved through
+/// PublicOnlyResolver so neither a redirect nor DNS rebinding can reach an internal host.
+pub fn guarded_client(allowed_hosts: &[String], allow_private: bool) -> reqwest::Result<reqwest::Client> {
+    let hop_allowed_hosts = allowed_hosts.to_vec();
+    let redirect_policy = reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() > MAX_REDIRECTS {
+            return attempt.error(format!("more than {MAX_REDIRECTS} redirects"));
+        }
+        match check_url(attempt.url(), &hop_allowed_hosts, allow_private) {
+            Ok(_) => attempt.follow(),
+            Err(denied) => attempt.error(denied),
+        }
+    });
+
+    let mut builder = crate::outbound::OutboundSettings::from_env()
+        .client_builder()
+        .redirect(redirect_policy);
+    if !allow_private {
+        builder = builder.dns_resolver(Arc::new(PublicOnlyResolver));
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_allowlist() {
+        let allowed = parse_allowed_hosts(" example.org, *.data.gov ,,");
+        assert_eq!(allowed, vec!["example.org", "data.gov"]);
+        assert!(host_allowed("example.org", &allowed));
+        assert!(host_allowed("feeds.Example.org", &allowed));
+        assert!(host_allowed("api.data.gov", &allowed));
+        assert!(!host_allowed("notexample.org", &allowed));
+        assert!(!host_allowed("example.org.evil.com", &allowed));
+        assert!(host_allowed("anything.com", &[]));
+    }
+
+    #[test]
+    fn test_private_addresses() {
+        let private = [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0",
+            "224.0.0.1", "239.255.255.250", "198.18.0.1", "198.19.255.254",
+            "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1", "::127.0.0.1", "::a9fe:a9fe",
+            "64:ff9b::10.0.0.1", "64:ff9b::a9fe:a9fe", "2002:a00:1::", "2002:7f00:1::1",
+        ];
+        for ip in private {
+            assert!(is_private_address(ip.parse().unwrap()), "{ip} should be private");
+        }
+        for ip in ["93.184.216.34", "8.8.8.8", "198.20.0.1", "2606:4700:4700::1111", "64:ff9b::808:808", "2002:808:808::"] {
+            assert!(!is_private_address(ip.parse().unwrap()), "{ip} should be public");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_destination() {
+        assert_eq!(
+            check_destination("http://169.254.169.254/latest/meta-data", &[], false).await,
+            Err(ProxyDenied::PrivateAddress("169.254.169.254".to_string()))
+        );
+        assert!(matches!(check_destination("http://localhost:5432/", &[], false).await, Err(ProxyDenied::PrivateAddress(_))));
+        assert!(check_destination("http://127.0.0.1:8081/", &[], true).await.is_ok());
+        assert!(matches!(check_destination("ftp://example.org/", &[], false).await, Err(ProxyDenied::InvalidUrl(_))));
+        assert_eq!(
+            check_destination("https://93.184.216.34/", &["example.org".to_string()], false).await,
+            Err(ProxyDenied::HostNotAllowed("93.184.216.34".to_string()))
+        );
+        assert!(check_destination("https://93.184.216.34/", &[], false).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_checked_on_every_hop() {
+        let mut server = mockito::Server::new_async().await;
+        let port = server.socket_address().port();
+        server
+            .mock("GET", "/elsewhere")
+            .with_status(302)
+            .with_header("location", &format!("http://localhost:{port}/ok"))
+            .create_async()
+            .await;
+        server.mock("GET", "/ok").with_body("ok").create_async().await;
+
+        // Private addresses are allowed here only so the test can reach the mock server
+        let allowed = vec!["127.0.0.1".to_string()];
+        let client = guarded_client(&allowed, true).unwrap();
+        let error = client.get(format!("{}/elsewhere", server.url())).send().await.unwrap_err();
+        assert!(error.is_redirect());
+        assert!(format!("{error:?}").contains("HostNotAllowed"));
+
+        let client = guarded_client(&[], false).unwrap();
+        let error = client.get(format!("http://localhost:{port}/ok")).send().await.unwrap_err();
+        assert!(format!("{error:?}").contains("PrivateAddress"));
+
+        let url = Url::parse("http://169.254.169.254/latest/meta-data").unwrap();
+        assert_eq!(check_url(&url, &[], false), Err(ProxyDenied::PrivateAddress("169.254.169.254".to_string())));
+        assert!(check_url(&url, &[], true).is_ok());
+    }
+}
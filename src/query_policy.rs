@@ -0,0 +1,327 @@
+// src/query_policy.rs
+// Validation rules for the read-only database query console
+
+use regex::Regex;
+
+/// Functions and catalog tables that a SELECT can use to sleep, read server files,
+/// touch large objects, or expose credentials
+const DENIED_PATTERNS: &[(&str, &str)] = &[
+    (r"\bpg_sleep\w*\b", "pg_sleep"),
+    (r"\bpg_read_file\b", "pg_read_file"),
+    (r"\bpg_read_binary_file\b", "pg_read_binary_file"),
+    (r"\bpg_ls_\w+\b", "pg_ls_* directory listing"),
+    (r"\blo_\w+\b", "large object functions (lo_*)"),
+    (r"\bpg_authid\b", "pg_authid"),
+    (r"\bpg_shadow\b", "pg_shadow"),
+    (r"\bpg_user_mappings?\b", "pg_user_mapping"),
+    (r"\bdblink\w*\b", "dblink"),
+    (r"\bpg_terminate_backend\b", "pg_terminate_backend"),
+    (r"\bpg_cancel_backend\b", "pg_cancel_backend"),
+    (r"\bset_config\b", "set_config"),
+    (r"\binto\b", "SELECT ... INTO"),
+    (r"\bfor\s+(update|share|no\s+key\s+update|key\s+share)\b", "row locking clauses"),
+];
+
+/// Tables holding login credentials. The console refuses queries naming them, and the
+/// table export and ?sample= endpoints refuse to read their rows.
+pub const DENIED_TABLES: &[&str] = &["sessions"];
+
+/// Words that are followed by parentheses but are not function calls
+const NON_FUNCTION_KEYWORDS: &[&str] = &[
+    "select", "from", "where", "and", "or", "not", "in", "exists", "any", "all", "some",
+    "as", "on", "using", "join", "over", "filter", "within", "values", "case", "when",
+    "then", "else", "by", "having", "union", "intersect", "except", "lateral", "cast",
+];
+
+/// Rules applied to console queries. The deny-list always applies; when
+/// QUERY_ALLOWED_FUNCTIONS is set, only the listed functions may be called.
+#[derive(Debug, Default, Clone)]
+pub struct QueryPolicy {
+    /// Extra identifiers to reject, from QUERY_DENIED_IDENTIFIERS (comma separated)
+    pub extra_denied: Vec<String>,
+    /// Allowlist mode, from QUERY_ALLOWED_FUNCTIONS (comma separated)
+    pub allowed_functions: Option<Vec<String>>,
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_lowercase())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+impl QueryPolicy {
+    pub fn from_env() -> Self {
+        QueryPolicy {
+            extra_denied: std::env::var("QUERY_DENIED_IDENTIFIERS")
+                .map(|v| parse_list(&v))
+                .unwrap_or_default(),
+            allowed_functions: std::env::var("QUERY_ALLOWED_FUNCTIONS")
+                .ok()
+                .map(|v| parse_list(&v))
+                .filter(|list| !list.is_empty()),
+        }
+    }
+
+    /// True if rows of `table` (optionally schema-qualified) may not be read through the
+    /// console, the table export or ?sample=
+    pub fn denies_table(&self, table: &str) -> bool {
+        let table = table.trim().to_lowercase();
+        let bare_name = table.rsplit('.').next().unwrap_or(&table);
+        DENIED_TABLES.contains(&bare_name) || self.extra_denied.iter().any(|d| *d == table || d == bare_name)
+    }
+
+    /// Returns a message describing why the query is rejected, if it is
+    pub fn validate(&self, query: &str) -> Result<(), String> {
+        let normalized = normalize_query(query);
+
+        if !normalized.starts_with("select") {
+            return Err("Only SELECT queries are allowed".to_string());
+        }
+
+        // A trailing semicolon is fine; a second statement is not
+        if normalized.trim_end_matches(|c: char| c == ';' || c.is_whitespace()).contains(';') {
+            return Err("Only a single statement is allowed".to_string());
+        }
+
+        for (pattern, label) in DENIED_PATTERNS {
+            if Regex::new(pattern).unwrap().is_match(&normalized) {
+                return Err(format!("Query uses {label}, which is not allowed in the query console"));
+            }
+        }
+
+        for identifier in DENIED_TABLES.iter().copied().chain(self.extra_denied.iter().map(String::as_str)) {
+            let pattern = format!(r"\b{}\b", regex::escape(identifier));
+            if Regex::new(&pattern).unwrap().is_match(&normalized) {
+                return Err(format!("Query uses {identifier}, which is not allowed in the query console"));
+            }
+        }
+
+        if let Some(allowed) = &self.allowed_functions {
+            let call = Regex::new(r"([a-z_][a-z0-9_$]*(?:\.[a-z_][a-z0-9_$]*)?)\s*\(").unwrap();
+            for captures in call.captures_iter(&normalized) {
+                let name = &captures[1];
+                let bare_name = name.rsplit('.').next().unwrap_or(name);
+                if NON_FUNCTION_KEYWORDS.contains(&bare_name) {
+                    continue;
+                }
+                if !allowed.iter().any(|a| a == name || a == bare_name) {
+                    return Err(format!(
+                        "Function {name} is not in the allowed list ({})",
+                        allowed.join(", ")
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Check a statement for the admin write endpoint: a single INSERT, UPDATE or DELETE
+/// (optionally behind a WITH clause). Schema changes and other commands are refused.
+pub fn validate_write(query: &str) -> Result<(), String> {
+    let normalized = normalize_query(query);
+
+    if normalized.trim_end_matches(|c: char| c == ';' || c.is_whitespace()).contains(';') {
+        return Err("Only a single statement is allowed".to_string());
+    }
+
+    let first_word = normalized.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or("");
+    match first_word {
+        "insert" | "update" | "delete" => Ok(()),
+        "with" if Regex::new(r"\b(insert|update|delete)\b").unwrap().is_match(&normalized) => Ok(()),
+        _ => Err("Only INSERT, UPDATE or DELETE statements are allowed".to_string()),
+    }
+}
+
+/// Lowercase, drop comments and string literal contents, and unquote identifiers
+/// so the checks above see what Postgres will actually resolve. E'...' escape
+/// strings and $tag$...$tag$ dollar quotes are skipped with their own rules, so a
+/// quote inside them cannot end the literal early and expose what follows.
+fn normalize_query(query: &str) -> String {
+    let chars: Vec<char> = query.chars().collect();
+    let mut output = String::with_capacity(query.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '-' if next == Some('-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                output.push(' ');
+            }
+            '/' if next == Some('*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+                output.push(' ');
+                continue;
+            }
+            '\'' => {
+                // E'...' (or e'...') treats backslash as an escape, so \' does not close it
+                let escape_string = output.ends_with('e')
+                    && !output[..output.len() - 1].ends_with(|p: char| p.is_alphanumeric() || p == '_' || p == '$');
+                i += 1;
+                while i < chars.len() {
+                    match chars[i] {
+                        '\\' if escape_string => i += 1,
+                        '\'' if chars.get(i + 1) == Some(&'\'') => i += 1,
+                        '\'' => break,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                // Keep an empty literal in place of the contents
+                output.push_str("''");
+            }
+            '$' if !output.ends_with(|p: char| p.is_alphanumeric() || p == '_' || p == '$') => {
+                match dollar_quote_tag(&chars[i..]) {
+                    Some(tag) => {
+                        i += tag.len();
+                        while i < chars.len() && !chars[i..].starts_with(&tag) {
+                            i += 1;
+                        }
+                        i += tag.len();
+                        output.push_str("''");
+                        continue;
+                    }
+                    // $1-style parameters and stray dollars pass through
+                    None => output.push('$'),
+                }
+            }
+            '"' => {
+                // Quoted identifiers keep their contents; "" is an escaped quote
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == '"' {
+                        if chars.get(i + 1) == Some(&'"') {
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                    output.extend(chars[i].to_lowercase());
+                    i += 1;
+                }
+            }
+            _ => output.extend(c.to_lowercase()),
+        }
+        i += 1;
+    }
+
+    output.trim().to_string()
+}
+
+/// The opening delimiter of a dollar-quoted string ($$ or $tag$) at the start of `chars`
+fn dollar_quote_tag(chars: &[char]) -> Option<Vec<char>> {
+    let end = chars.iter().skip(1).position(|&c| c == '$')? + 1;
+    let tag = &chars[1..end];
+    let valid = tag.first().is_none_or(|c| c.is_alphabetic() || *c == '_')
+        && tag.iter().all(|c| c.is_alphanumeric() || *c == '_');
+    valid.then(|| chars[..=end].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rejects(query: &str) -> bool {
+        QueryPolicy::default().validate(query).is_err()
+    }
+
+    #[test]
+    fn test_blocks_denied_patterns() {
+        assert!(rejects("SELECT pg_sleep(10)"));
+        assert!(rejects("select pg_sleep_for('5 minutes')"));
+        assert!(rejects("SELECT pg_read_file('/etc/passwd')"));
+        assert!(rejects("SELECT pg_read_binary_file('/etc/passwd')"));
+        assert!(rejects("SELECT * FROM pg_ls_dir('.')"));
+        assert!(rejects("SELECT lo_import('/etc/passwd')"));
+        assert!(rejects("SELECT lo_export(1234, '/tmp/x')"));
+        assert!(rejects("SELECT rolpassword FROM pg_authid"));
+        assert!(rejects("SELECT passwd FROM pg_catalog.\"pg_shadow\""));
+        assert!(rejects("SELECT * FROM dblink('host=x', 'select 1') AS t(a int)"));
+        assert!(rejects("SELECT set_config('statement_timeout', '0', false)"));
+        assert!(rejects("SELECT * INTO copy_of_users FROM users"));
+        assert!(rejects("SELECT * FROM users FOR UPDATE"));
+    }
+
+    #[test]
+    fn test_blocks_statement_tricks() {
+        assert!(rejects("DELETE FROM users"));
+        assert!(rejects("SELECT 1; DROP TABLE users"));
+        assert!(rejects("/* select */ DELETE FROM users"));
+        assert!(rejects("SELECT 1 -- harmless\n; UPDATE users SET name = 'x'"));
+    }
+
+    #[test]
+    fn test_allows_ordinary_selects() {
+        assert!(!rejects("SELECT id, name FROM projects WHERE status = 'Active' ORDER BY name LIMIT 10;"));
+        assert!(!rejects("SELECT count(*) FROM accounts WHERE name IN ('a', 'b')"));
+        // Denied names inside string literals and comments are harmless
+        assert!(!rejects("SELECT * FROM notes WHERE body = 'call pg_sleep(5) into the log' -- pg_authid"));
+        assert!(!rejects("SELECT E'it\\'s pg_sleep', $$pg_read_file$$, $body$ 'x' $body$ FROM notes WHERE id = $1"));
+    }
+
+    #[test]
+    fn test_blocks_calls_hidden_behind_literal_quoting() {
+        // E-strings end only at an unescaped quote, so these calls sit outside the literal
+        assert!(rejects("SELECT E'\\'', pg_sleep(5) --'"));
+        assert!(rejects("SELECT e'\\'', pg_read_file('/etc/passwd') --'"));
+        // Dollar quotes do not close at a single quote
+        assert!(rejects("SELECT $$'$$, pg_sleep(5) --'"));
+        assert!(rejects("SELECT $x$'$x$, pg_read_file('/etc/passwd') --'"));
+        // A quote inside a quoted identifier does not open a literal
+        assert!(rejects("SELECT 1 AS \"a'b\", pg_sleep(5), 'c'"));
+    }
+
+    #[test]
+    fn test_validate_write() {
+        assert!(validate_write("INSERT INTO accounts (name) VALUES ('Acme');").is_ok());
+        assert!(validate_write("  update projects SET status = 'Closed' WHERE id = $1").is_ok());
+        assert!(validate_write("WITH old AS (SELECT id FROM notes) DELETE FROM notes USING old WHERE notes.id = old.id").is_ok());
+        assert!(validate_write("DROP TABLE accounts").is_err());
+        assert!(validate_write("SELECT 1").is_err());
+        assert!(validate_write("DELETE FROM notes; DROP TABLE notes").is_err());
+        assert!(validate_write("/* delete */ TRUNCATE notes").is_err());
+    }
+
+    #[test]
+    fn test_extra_denied_identifiers() {
+        let policy = QueryPolicy {
+            extra_denied: vec!["users".to_string()],
+            allowed_functions: None,
+        };
+        assert!(policy.validate("SELECT * FROM users").is_err());
+        assert!(policy.validate("SELECT * FROM users_roles").is_ok());
+        assert!(policy.denies_table("Users"));
+    }
+
+    #[test]
+    fn test_sessions_table_denied_by_default() {
+        assert!(rejects("SELECT id, user_json FROM sessions"));
+        assert!(rejects("SELECT * FROM public.\"sessions\""));
+        let policy = QueryPolicy::default();
+        assert!(policy.denies_table("sessions"));
+        assert!(policy.denies_table("public.sessions"));
+        assert!(!policy.denies_table("projects"));
+    }
+
+    #[test]
+    fn test_allowlist_mode() {
+        let policy = QueryPolicy {
+            extra_denied: Vec::new(),
+            allowed_functions: Some(vec!["count".to_string(), "lower".to_string()]),
+        };
+        assert!(policy.validate("SELECT count(*), lower(name) FROM projects WHERE id IN (1, 2)").is_ok());
+        assert!(policy.validate("SELECT version()").is_err());
+        assert!(policy.validate("SELECT pg_catalog.current_setting('data_directory')").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+// src/rate_limit.rs
+// Per-client token buckets for the endpoints that spend AI provider quota
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Buckets untouched for this long are full again and can be forgotten
+const IDLE_BUCKET_SECS: f64 = 60.0;
+/// Sweep idle buckets once the map grows past this many clients
+const SWEEP_THRESHOLD: usize = 1024;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// One bucket per client IP. Each holds up to `per_minute` requests and refills
+/// continuously, so a client can burst to the limit and then gets one request
+/// every 60/per_minute seconds.
+#[derive(Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Take a token for `client`, or return how many seconds until one is available
+    pub fn check(&self, client: IpAddr, per_minute: u32, now: Instant) -> Result<(), u64> {
+        let capacity = f64::from(per_minute);
+        let refill_per_sec = capacity / 60.0;
+        let mut buckets = self.buckets.lock().unwrap();
+
+        if buckets.len() > SWEEP_THRESHOLD {
+            buckets.retain(|_, bucket| now.saturating_duration_since(bucket.updated).as_secs_f64() < IDLE_BUCKET_SECS);
+        }
+
+        let bucket = buckets.entry(client).or_insert(Bucket { tokens: capacity, updated: now });
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        // min() also shrinks buckets when the limit is lowered by a config reload
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_sec).min(capacity);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(((1.0 - bucket.tokens) / refill_per_sec).ceil().max(1.0) as u64)
+        }
+    }
+}
+
+/// Middleware for the AI endpoints: answers 429 with Retry-After once a client IP
+/// exceeds AI_RATE_LIMIT_PER_MINUTE. A limit of 0 turns it off.
+pub async fn limit_ai_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(state) = req.app_data::<web::Data<Arc<crate::ApiState>>>().cloned() else {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    };
+    let per_minute = state.config.lock().unwrap().ai_rate_limit_per_minute;
+    if per_minute == 0 {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+
+    let client = req.peer_addr().map(|addr| addr.ip()).unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    match state.ai_rate_limiter.check(client, per_minute, Instant::now()) {
+        Ok(()) => next.call(req).await.map(ServiceResponse::map_into_left_body),
+        Err(retry_after) => {
+            log::warn!("AI rate limit exceeded for {client} on {}", req.path());
+            let response = HttpResponse::TooManyRequests()
+                .insert_header(("Retry-After", retry_after.to_string()))
+                .json(json!({
+                    "success": false,
+                    "error": format!("Rate limit of {per_minute} AI requests per minute exceeded; retry in {retry_after}s")
+                }));
+            Ok(req.into_response(response).map_into_right_body())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_bucket_allows_burst_then_refills() {
+        let limiter = RateLimiter::default();
+        let start = Instant::now();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let other: IpAddr = "203.0.113.8".parse().unwrap();
+
+        for _ in 0..30 {
+            assert_eq!(limiter.check(client, 30, start), Ok(()));
+        }
+        // 30/minute refills one token every 2 seconds
+        assert_eq!(limiter.check(client, 30, start), Err(2));
+        assert_eq!(limiter.check(client, 30, start + Duration::from_secs(1)), Err(1));
+        assert_eq!(limiter.check(client, 30, start + Duration::from_secs(2)), Ok(()));
+        assert_eq!(limiter.check(client, 30, start + Duration::from_secs(2)), Err(2));
+
+        // Other clients have their own bucket
+        assert_eq!(limiter.check(other, 30, start), Ok(()));
+    }
+}
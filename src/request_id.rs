@@ -0,0 +1,95 @@
+// src/request_id.rs
+// Per-request correlation ids: assigned by middleware, echoed in X-Request-Id and added
+// to every log line written while the request is being handled
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use std::io::Write;
+
+pub const HEADER: &str = "x-request-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Id of the request the current task is handling, if any
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(String::clone).ok()
+}
+
+/// "[req <id>] " prefix for log lines, or "" outside a request
+pub fn tag() -> String {
+    current().map(|id| format!("[req {id}] ")).unwrap_or_default()
+}
+
+// Reuse an id sent by a proxy or client when it is short and plain, so a trace can span
+// services; anything else gets a fresh UUID
+fn incoming_id(req: &ServiceRequest) -> Option<String> {
+    let id = req.headers().get(HEADER)?.to_str().ok()?.trim();
+    let plain = !id.is_empty()
+        && id.len() <= 64
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    plain.then(|| id.to_string())
+}
+
+/// Middleware giving each request an id, visible to handlers through `current()` and
+/// returned to the client in X-Request-Id
+pub async fn assign_request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let id = incoming_id(&req).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let mut response = REQUEST_ID.scope(id.clone(), next.call(req)).await?;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(HeaderName::from_static(HEADER), value);
+    }
+    Ok(response)
+}
+
+/// env_logger format: the default layout plus the request id when there is one
+pub fn format_log_line(buf: &mut env_logger::fmt::Formatter, record: &log::Record) -> std::io::Result<()> {
+    writeln!(
+        buf,
+        "[{} {:<5} {}] {}{}",
+        buf.timestamp(),
+        record.level(),
+        record.target(),
+        tag(),
+        record.args()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::{call_service, init_service, read_body, TestRequest};
+    use actix_web::{middleware::from_fn, web, App, HttpResponse};
+
+    #[tokio::test]
+    async fn test_request_id_is_returned_and_visible_to_handlers() {
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(assign_request_id))
+                .route("/id", web::get().to(|| async { HttpResponse::Ok().body(current().unwrap_or_default()) })),
+        )
+        .await;
+
+        let response = call_service(&app, TestRequest::get().uri("/id").to_request()).await;
+        let header = response.headers().get(HEADER).unwrap().to_str().unwrap().to_string();
+        assert!(uuid::Uuid::parse_str(&header).is_ok());
+        assert_eq!(read_body(response).await, header.as_bytes());
+
+        // A plain incoming id is kept; anything else is replaced
+        let request = TestRequest::get().uri("/id").insert_header((HEADER, "edge-1234")).to_request();
+        let response = call_service(&app, request).await;
+        assert_eq!(response.headers().get(HEADER).unwrap(), "edge-1234");
+        let request = TestRequest::get().uri("/id").insert_header((HEADER, "bad id\twith spaces")).to_request();
+        let response = call_service(&app, request).await;
+        assert_ne!(response.headers().get(HEADER).unwrap(), "bad id\twith spaces");
+
+        assert_eq!(current(), None);
+        assert_eq!(tag(), "");
+    }
+}
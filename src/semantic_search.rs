@@ -1,11 +1,19 @@
 // src/semantic_search.rs
 // Semantic search handler with server-side business logic
 
-use actix_web::{web, HttpResponse, Result};
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, Result};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
-use crate::prompts::{build_semantic_search_prompt, ProjectData};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres, Row};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+use crate::prompts::{build_semantic_search_prompt, check_prompt_size, is_near_prompt_limit, ProjectData};
 use crate::gemini_insights::{self, GeminiAnalysisRequest};
 use crate::claude_insights::{self, ClaudeAnalysisRequest};
+use crate::openai_insights;
 use crate::ApiState;
 
 /// Request payload for semantic search
@@ -14,7 +22,7 @@ pub struct SemanticSearchRequest {
     /// User's search query
     pub query: String,
 
-    /// AI provider to use ('gemini' or 'claude')
+    /// AI provider to use ('gemini', 'claude' or 'openai'), or 'keyword' for local matching without AI
     #[serde(default = "default_provider")]
     pub provider: String,
 
@@ -23,7 +31,7 @@ pub struct SemanticSearchRequest {
     pub filters: SearchFilters,
 
     /// Optional: all projects data from client
-    /// If not provided, server should load from database/external source
+    /// If not provided, the server loads them from the projects table
     pub projects: Option<Vec<ProjectData>>,
 }
 
@@ -32,7 +40,7 @@ fn default_provider() -> String {
 }
 
 /// Search filters (extensible for future use)
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct SearchFilters {
     /// Maximum number of projects to analyze
     #[serde(default = "default_max_results")]
@@ -43,14 +51,25 @@ pub struct SearchFilters {
 
     /// Optional status filter
     pub status: Option<Vec<String>>,
+
+    /// Drop matches scored below this (0-100); unscored matches are kept
+    #[serde(default)]
+    pub min_relevance: Option<u32>,
 }
 
 fn default_max_results() -> usize {
     30
 }
 
+// Used when the request has no `filters` object, so max_results must match the field default
+impl Default for SearchFilters {
+    fn default() -> Self {
+        SearchFilters { max_results: default_max_results(), teams: None, status: None, min_relevance: None }
+    }
+}
+
 /// Match result from semantic search
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchMatch {
     pub title: String,
     pub description: String,
@@ -79,6 +98,16 @@ impl From<gemini_insights::TokenUsage> for TokenUsage {
     }
 }
 
+impl From<openai_insights::TokenUsage> for TokenUsage {
+    fn from(usage: openai_insights::TokenUsage) -> Self {
+        TokenUsage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+        }
+    }
+}
+
 impl From<claude_insights::TokenUsage> for TokenUsage {
     fn from(usage: claude_insights::TokenUsage) -> Self {
         TokenUsage {
@@ -90,7 +119,7 @@ impl From<claude_insights::TokenUsage> for TokenUsage {
 }
 
 /// Response payload for semantic search
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SemanticSearchResponse {
     pub success: bool,
     pub matches: Option<Vec<SearchMatch>>,
@@ -98,6 +127,81 @@ pub struct SemanticSearchResponse {
     pub search_interpretation: Option<String>,
     pub error: Option<String>,
     pub token_usage: Option<TokenUsage>,
+    /// Served from the search cache without calling the provider; only sent when true
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+}
+
+/// Entries kept before the least recently used one is evicted
+const SEARCH_CACHE_MAX_ENTRIES: usize = 200;
+
+struct CachedSearch {
+    response: SemanticSearchResponse,
+    stored_at: Instant,
+    last_used: Instant,
+}
+
+/// Successful AI search responses keyed by `cache_key`, reused for SEARCH_CACHE_TTL_SECS
+#[derive(Default)]
+pub struct SearchCache {
+    entries: Mutex<HashMap<String, CachedSearch>>,
+}
+
+impl SearchCache {
+    pub fn get(&self, key: &str, ttl: std::time::Duration) -> Option<SemanticSearchResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        let cached = entries.get_mut(key).filter(|cached| cached.stored_at.elapsed() < ttl)?;
+        cached.last_used = Instant::now();
+        Some(cached.response.clone())
+    }
+
+    pub fn insert(&self, key: String, response: SemanticSearchResponse, ttl: std::time::Duration) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, cached| cached.stored_at.elapsed() < ttl);
+        if entries.len() >= SEARCH_CACHE_MAX_ENTRIES {
+            if let Some(oldest) = entries.iter().min_by_key(|(_, cached)| cached.last_used).map(|(key, _)| key.clone()) {
+                entries.remove(&oldest);
+            }
+        }
+        let now = Instant::now();
+        entries.insert(key, CachedSearch { response, stored_at: now, last_used: now });
+    }
+}
+
+/// Hash of everything that decides a search's answer: the query (trimmed, lowercased,
+/// whitespace collapsed), the provider, the projects it searched and the filters
+pub fn cache_key(query: &str, provider: &str, projects: &[ProjectData], filters: &SearchFilters) -> String {
+    let query = query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let mut hasher = Sha256::new();
+    for part in [
+        query,
+        provider.to_string(),
+        serde_json::to_string(projects).unwrap_or_default(),
+        serde_json::to_string(filters).unwrap_or_default(),
+    ] {
+        // Length prefixes keep ("ab", "c") and ("a", "bc") apart
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Stage updates for the streaming endpoint; the default sends nothing
+#[derive(Clone, Default)]
+struct Progress(Option<tokio::sync::mpsc::UnboundedSender<web::Bytes>>);
+
+impl Progress {
+    fn emit(&self, event: &str, data: serde_json::Value) {
+        if let Some(sender) = &self.0 {
+            // A closed receiver means the client went away; the search still finishes and is logged
+            let _ = sender.send(sse_event(event, &data));
+        }
+    }
+}
+
+/// One Server-Sent Event; compact JSON never contains a newline, so `data` fits on one line
+fn sse_event(event: &str, data: &serde_json::Value) -> web::Bytes {
+    web::Bytes::from(format!("event: {event}\ndata: {data}\n\n"))
 }
 
 /// Main semantic search handler
@@ -110,71 +214,271 @@ pub struct SemanticSearchResponse {
 /// 5. Parses and validates response
 /// 6. Returns structured results
 pub async fn search_projects(
+    http_req: HttpRequest,
     data: web::Data<std::sync::Arc<ApiState>>,
     req: web::Json<SemanticSearchRequest>,
 ) -> Result<HttpResponse> {
-    println!("📡 Semantic search request: query='{}', provider='{}'", req.query, req.provider);
+    let (status, response) = run_search(data.clone(), &req, &Progress::default()).await?;
+    let client_ip = http_req.peer_addr().map(|addr| addr.ip().to_string());
+    record_search(&data, &req, &response, client_ip.as_deref()).await;
+
+    Ok(HttpResponse::build(status).json(response))
+}
+
+/// Streaming variant of `search_projects` for the search UI
+///
+/// Answers with Server-Sent Events as the search moves through its stages
+/// (`projects_selected`, `prompt_built`, `calling_ai`, `parsing`), then a final `results`
+/// event holding the batch endpoint's response body plus its `status` code. Failures that
+/// the batch endpoint reports as an error response arrive as an `error` event.
+pub async fn search_projects_stream(
+    http_req: HttpRequest,
+    data: web::Data<std::sync::Arc<ApiState>>,
+    req: web::Json<SemanticSearchRequest>,
+) -> HttpResponse {
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    let progress = Progress(Some(sender));
+    let client_ip = http_req.peer_addr().map(|addr| addr.ip().to_string());
+    let req = req.into_inner();
+
+    // The stream ends when the task finishes and drops the sender. The Gemini path goes
+    // through its actix handler, whose response is not Send, so this runs on the worker's
+    // local task set rather than tokio::spawn
+    actix_web::rt::spawn(async move {
+        match run_search(data.clone(), &req, &progress).await {
+            Ok((status, response)) => {
+                record_search(&data, &req, &response, client_ip.as_deref()).await;
+                let mut body = serde_json::to_value(&response).unwrap_or_default();
+                body["status"] = json!(status.as_u16());
+                progress.emit("results", body);
+            }
+            Err(e) => progress.emit("error", json!({ "success": false, "error": e.to_string() })),
+        }
+    });
+
+    let events = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|event| (Ok::<_, std::io::Error>(event), receiver))
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events)
+}
+
+// Analytics logging never changes the response the client receives
+async fn record_search(
+    data: &ApiState,
+    req: &SemanticSearchRequest,
+    response: &SemanticSearchResponse,
+    client_ip: Option<&str>,
+) {
+    if !search_analytics_enabled() || req.query.trim().is_empty() {
+        return;
+    }
+    if let Some(pool) = &data.db {
+        let user_hash = anonymize_user(client_ip);
+        if let Err(e) = log_search(pool, &req.query, &req.provider, response, &user_hash).await {
+            log::warn!("⚠️ Failed to record search analytics: {}", e);
+        }
+    }
+}
+
+/// Runs the search pipeline and returns the status code alongside the response body
+async fn run_search(
+    data: web::Data<std::sync::Arc<ApiState>>,
+    req: &SemanticSearchRequest,
+    progress: &Progress,
+) -> Result<(StatusCode, SemanticSearchResponse)> {
+    log::info!("📡 Semantic search request: query='{}', provider='{}'", req.query, req.provider);
 
     // 1. Validate query
     if req.query.trim().is_empty() {
-        return Ok(HttpResponse::BadRequest().json(SemanticSearchResponse {
+        return Ok((StatusCode::BAD_REQUEST, SemanticSearchResponse {
             success: false,
             matches: None,
             total_matches: None,
             search_interpretation: None,
             error: Some("Search query cannot be empty".to_string()),
             token_usage: None,
+            cached: false,
         }));
     }
 
-    // 2. Get projects data
-    // In future, this could load from database or external API
-    let all_projects = match &req.projects {
-        Some(projects) => projects.clone(),
-        None => {
-            return Ok(HttpResponse::BadRequest().json(SemanticSearchResponse {
+    // 2. Get projects data, from the client or else the database
+    let all_projects = match (&req.projects, &data.db) {
+        (Some(projects), _) => projects.clone(),
+        (None, Some(pool)) => match load_projects(pool, &req.filters).await {
+            Ok(projects) => projects,
+            Err(e) => {
+                log::error!("❌ Failed to load projects for search: {}", e);
+                return Ok((StatusCode::INTERNAL_SERVER_ERROR, SemanticSearchResponse {
+                    success: false,
+                    matches: None,
+                    total_matches: None,
+                    search_interpretation: None,
+                    error: Some(format!("Failed to load projects: {}", e)),
+                    token_usage: None,
+                    cached: false,
+                }));
+            }
+        },
+        (None, None) => {
+            return Ok((StatusCode::BAD_REQUEST, SemanticSearchResponse {
                 success: false,
                 matches: None,
                 total_matches: None,
                 search_interpretation: None,
-                error: Some("No projects data provided. Client must send projects array.".to_string()),
+                error: Some("No projects data provided and no database is connected. Send a projects array.".to_string()),
                 token_usage: None,
+                cached: false,
             }));
         }
     };
 
-    println!("📊 Total projects available: {}", all_projects.len());
+    log::debug!("📊 Total projects available: {}", all_projects.len());
 
     // 3. Apply filters and select top projects for analysis
     let filtered_projects = apply_filters(&all_projects, &req.filters);
-    let projects_to_analyze = select_projects_for_analysis(&filtered_projects, req.filters.max_results);
 
-    println!("📋 Projects selected for analysis: {} of {}", projects_to_analyze.len(), all_projects.len());
+    // 4. The same AI search over the same projects is answered from the cache
+    let cache_ttl = std::time::Duration::from_secs(data.config.lock().unwrap().search_cache_ttl_secs);
+    let key = (req.provider != "keyword" && !cache_ttl.is_zero())
+        .then(|| cache_key(&req.query, &req.provider, &all_projects, &req.filters));
+    if let Some(mut response) = key.as_ref().and_then(|key| data.search_cache.get(key, cache_ttl)) {
+        log::debug!("♻️ Semantic search served from cache");
+        // Nothing was spent on this request
+        response.token_usage = None;
+        response.cached = true;
+        return Ok((StatusCode::OK, response));
+    }
+
+    // 5. Keyword mode ranks locally and is done; AI providers get the best-ranked projects
+    let (status, mut response) = if req.provider == "keyword" {
+        progress.emit("projects_selected", json!({ "selected": filtered_projects.len(), "total": all_projects.len() }));
+        (StatusCode::OK, keyword_search(&req.query, &filtered_projects, req.filters.max_results))
+    } else {
+        let projects_to_analyze = select_projects_for_analysis(&req.query, &filtered_projects, req.filters.max_results);
+        log::debug!("📋 Projects selected for analysis: {} of {}", projects_to_analyze.len(), all_projects.len());
+        progress.emit("projects_selected", json!({ "selected": projects_to_analyze.len(), "total": all_projects.len() }));
+        ask_provider(data.clone(), req, &projects_to_analyze, all_projects.len(), progress).await?
+    };
+
+    // 6. Drop weak matches when the client asked for a minimum score
+    if let Some(min_relevance) = req.filters.min_relevance {
+        apply_min_relevance(&mut response, min_relevance);
+    }
+
+    // 7. Keep successful AI answers for the next identical search
+    if let Some(key) = key {
+        if status == StatusCode::OK && response.success {
+            data.search_cache.insert(key, response.clone(), cache_ttl);
+        }
+    }
+
+    Ok((status, response))
+}
 
-    // 4. Build prompt using server-side template
-    let prompt = build_semantic_search_prompt(
-        &req.query,
-        &projects_to_analyze,
-        all_projects.len(),
-    );
+/// Build the prompt using the server-side template and send it to the requested provider
+async fn ask_provider(
+    data: web::Data<std::sync::Arc<ApiState>>,
+    req: &SemanticSearchRequest,
+    projects_to_analyze: &[ProjectData],
+    total_projects: usize,
+    progress: &Progress,
+) -> Result<(StatusCode, SemanticSearchResponse)> {
+    let prompt = build_semantic_search_prompt(&req.query, projects_to_analyze, total_projects);
 
-    println!("📝 Prompt generated: {} characters", prompt.len());
+    log::debug!("📝 Prompt generated: {} characters", prompt.len());
+
+    // The selected projects make up most of the prompt, so max_results is the lever here
+    let max_prompt_chars = data.config.lock().unwrap().max_prompt_chars;
+    if let Err(reason) = check_prompt_size(&prompt, max_prompt_chars) {
+        log::warn!("⚠️ Semantic search prompt for {} projects refused: {}", projects_to_analyze.len(), reason);
+        return Ok((StatusCode::PAYLOAD_TOO_LARGE, SemanticSearchResponse {
+            success: false,
+            matches: None,
+            total_matches: None,
+            search_interpretation: None,
+            error: Some(format!("{reason}. Lower filters.max_results to send fewer projects.")),
+            token_usage: None,
+            cached: false,
+        }));
+    }
+    if is_near_prompt_limit(&prompt, max_prompt_chars) {
+        log::warn!(
+            "⚠️ Semantic search prompt for {} projects is {} of {} allowed characters",
+            projects_to_analyze.len(),
+            prompt.chars().count(),
+            max_prompt_chars
+        );
+    }
+
+    progress.emit("prompt_built", json!({ "characters": prompt.chars().count() }));
+    if matches!(req.provider.as_str(), "gemini" | "claude" | "openai") {
+        progress.emit("calling_ai", json!({ "provider": req.provider }));
+    }
 
-    // 5. Call AI API based on provider
     match req.provider.as_str() {
-        "gemini" => call_gemini_for_search(data, &prompt).await,
-        "claude" => call_claude_for_search(&prompt).await,
-        _ => Ok(HttpResponse::BadRequest().json(SemanticSearchResponse {
+        "gemini" => call_gemini_for_search(data, &prompt, progress).await,
+        "claude" => {
+            let backend = crate::claude_insights::ClaudeBackend::from_state(&data);
+            call_claude_for_search(&data.http_client, &backend, &prompt, progress).await
+        }
+        "openai" => {
+            let api_key = data.config.lock().unwrap().openai_api_key.clone();
+            call_openai_for_search(&data.http_client, &api_key, &prompt, progress).await
+        }
+        _ => Ok((StatusCode::BAD_REQUEST, SemanticSearchResponse {
             success: false,
             matches: None,
             total_matches: None,
             search_interpretation: None,
-            error: Some(format!("Invalid provider: {}. Use 'gemini' or 'claude'", req.provider)),
+            error: Some(format!("Invalid provider: {}. Use 'gemini', 'claude', 'openai' or 'keyword'", req.provider)),
             token_usage: None,
+            cached: false,
         })),
     }
 }
 
+/// Remove matches scored below `min_relevance`, keeping total_matches consistent
+fn apply_min_relevance(response: &mut SemanticSearchResponse, min_relevance: u32) {
+    if let Some(matches) = response.matches.as_mut() {
+        let before = matches.len();
+        matches.retain(|m| m.relevance_score.is_none_or(|score| score >= min_relevance));
+        let dropped = before - matches.len();
+        response.total_matches = response.total_matches.map(|total| total.saturating_sub(dropped).max(matches.len()));
+    }
+}
+
+/// Load up to `filters.max_results` projects from the database, most recently modified first
+///
+/// The status filter is applied in SQL so the limit counts only matching projects.
+/// The table has no team column, so the team filter has nothing to act on here.
+async fn load_projects(pool: &Pool<Postgres>, filters: &SearchFilters) -> Result<Vec<ProjectData>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT name, description, status FROM projects
+         WHERE NOT deleted AND ($1::text[] IS NULL OR status = ANY($1))
+         ORDER BY date_modified DESC NULLS LAST
+         LIMIT $2"
+    )
+    .bind(filters.status.as_deref())
+    .bind(filters.max_results as i64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| ProjectData {
+            title: row.get::<Option<String>, _>("name").unwrap_or_default(),
+            description: row.get::<Option<String>, _>("description").unwrap_or_default(),
+            team: None,
+            status: row.get("status"),
+            tags: None,
+            url: None,
+        })
+        .collect())
+}
+
 /// Apply filters to projects
 fn apply_filters(projects: &[ProjectData], filters: &SearchFilters) -> Vec<ProjectData> {
     projects.iter()
@@ -203,34 +507,133 @@ fn apply_filters(projects: &[ProjectData], filters: &SearchFilters) -> Vec<Proje
         .collect()
 }
 
-/// Select projects for analysis
+/// Select projects for analysis: the best keyword matches first, then the rest in their
+/// original order, so the AI sees likely candidates even when the list is truncated
 ///
 /// Future improvements could include:
-/// - Relevance ranking before sending to AI
 /// - Prioritizing recently updated projects
 /// - Ensuring diverse team representation
-fn select_projects_for_analysis(projects: &[ProjectData], max_results: usize) -> Vec<ProjectData> {
-    projects.iter()
+fn select_projects_for_analysis(query: &str, projects: &[ProjectData], max_results: usize) -> Vec<ProjectData> {
+    rank_projects(query, projects)
+        .into_iter()
         .take(max_results)
-        .cloned()
+        .map(|ranked| ranked.project.clone())
         .collect()
 }
 
+/// Words too common to say anything about a match
+const STOP_WORDS: &[&str] = &["and", "for", "the", "with", "that", "this", "from", "are", "about", "projects", "project"];
+
+/// Lowercased query terms worth matching on
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for term in query.split(|c: char| !c.is_alphanumeric()).map(str::to_lowercase) {
+        if term.chars().count() >= 3 && !STOP_WORDS.contains(&term.as_str()) && !terms.contains(&term) {
+            terms.push(term);
+        }
+    }
+    terms
+}
+
+struct RankedProject<'a> {
+    project: &'a ProjectData,
+    score: u32,
+    matched_terms: Vec<String>,
+}
+
+/// Score every project by which query terms it contains, weighting the title (3) over
+/// tags (2) and team/description (1). Sorted best first; ties keep the input order.
+fn rank_projects<'a>(query: &str, projects: &'a [ProjectData]) -> Vec<RankedProject<'a>> {
+    let terms = query_terms(query);
+    let mut ranked: Vec<RankedProject> = projects
+        .iter()
+        .map(|project| {
+            let fields = [
+                (project.title.to_lowercase(), 3),
+                (project.tags.as_deref().unwrap_or_default().to_lowercase(), 2),
+                (project.team.as_deref().unwrap_or_default().to_lowercase(), 1),
+                (project.description.to_lowercase(), 1),
+            ];
+            let mut score = 0;
+            let mut matched_terms = Vec::new();
+            for term in &terms {
+                let weight: u32 = fields.iter().filter(|(text, _)| text.contains(term.as_str())).map(|(_, weight)| weight).sum();
+                if weight > 0 {
+                    score += weight;
+                    matched_terms.push(term.clone());
+                }
+            }
+            RankedProject { project, score, matched_terms }
+        })
+        .collect();
+    ranked.sort_by_key(|ranked| std::cmp::Reverse(ranked.score));
+    ranked
+}
+
+/// Search without AI: projects containing any query term, scored by the share of terms matched
+fn keyword_search(query: &str, projects: &[ProjectData], max_results: usize) -> SemanticSearchResponse {
+    let terms = query_terms(query);
+    let ranked: Vec<RankedProject> = rank_projects(query, projects)
+        .into_iter()
+        .filter(|ranked| ranked.score > 0)
+        .collect();
+    let total_matches = ranked.len();
+
+    // Coverage of the query decides the order; the weighted rank breaks ties
+    let mut matches: Vec<SearchMatch> = ranked
+        .into_iter()
+        .map(|ranked| {
+            let label = if ranked.matched_terms.len() == 1 { "matched term" } else { "matched terms" };
+            SearchMatch {
+                title: ranked.project.title.clone(),
+                description: ranked.project.description.clone(),
+                relevance_score: Some((ranked.matched_terms.len() * 100 / terms.len()) as u32),
+                match_reason: Some(format!("{}: {}", label, ranked.matched_terms.join(", "))),
+                url: ranked.project.url.clone(),
+                team: ranked.project.team.clone(),
+                status: ranked.project.status.clone(),
+            }
+        })
+        .collect();
+    matches.sort_by_key(|m| std::cmp::Reverse(m.relevance_score));
+    matches.truncate(max_results);
+
+    SemanticSearchResponse {
+        success: true,
+        matches: Some(matches),
+        total_matches: Some(total_matches),
+        search_interpretation: Some(if terms.is_empty() {
+            "No searchable terms in the query".to_string()
+        } else {
+            format!("Keyword match on: {}", terms.join(", "))
+        }),
+        error: None,
+        token_usage: None,
+        cached: false,
+    }
+}
+
 /// Call Gemini API for semantic search using existing handler
 async fn call_gemini_for_search(
     data: web::Data<std::sync::Arc<ApiState>>,
     prompt: &str,
-) -> Result<HttpResponse> {
+    progress: &Progress,
+) -> Result<(StatusCode, SemanticSearchResponse)> {
     // Use existing Gemini handler
     let gemini_request = GeminiAnalysisRequest {
         prompt: prompt.to_string(),
         data_context: None,
+        temperature: None,
+        top_k: None,
+        top_p: None,
+        max_output_tokens: None,
     };
 
     let response = gemini_insights::analyze_with_gemini(
         data,
         web::Json(gemini_request),
     ).await?;
+    let gemini_status = response.status();
 
     // Extract the response body
     if let Ok(body_bytes) = actix_web::body::to_bytes(response.into_body()).await {
@@ -238,119 +641,413 @@ async fn call_gemini_for_search(
             if gemini_response.success {
                 if let Some(analysis) = gemini_response.analysis {
                     // Parse AI response
+                    progress.emit("parsing", json!({ "characters": analysis.chars().count() }));
                     match parse_search_results(&analysis) {
                         Ok((matches, total_matches, interpretation)) => {
-                            return Ok(HttpResponse::Ok().json(SemanticSearchResponse {
+                            return Ok((StatusCode::OK, SemanticSearchResponse {
                                 success: true,
                                 matches: Some(matches),
                                 total_matches: Some(total_matches),
                                 search_interpretation: Some(interpretation),
                                 error: None,
                                 token_usage: gemini_response.token_usage.map(|u| u.into()),
+                                cached: false,
                             }));
                         }
                         Err(e) => {
-                            eprintln!("❌ Failed to parse AI response: {}", e);
-                            return Ok(HttpResponse::Ok().json(SemanticSearchResponse {
+                            log::error!("❌ Failed to parse AI response: {}", e);
+                            return Ok((StatusCode::UNPROCESSABLE_ENTITY, SemanticSearchResponse {
                                 success: false,
                                 matches: None,
                                 total_matches: None,
                                 search_interpretation: None,
                                 error: Some(format!("Failed to parse AI response: {}", e)),
                                 token_usage: gemini_response.token_usage.map(|u| u.into()),
+                                cached: false,
                             }));
                         }
                     }
                 }
             }
             // Return the error from Gemini
-            return Ok(HttpResponse::Ok().json(SemanticSearchResponse {
+            return Ok((provider_failure_status(gemini_status), SemanticSearchResponse {
                 success: false,
                 matches: None,
                 total_matches: None,
                 search_interpretation: None,
                 error: gemini_response.error,
                 token_usage: None,
+                cached: false,
             }));
         }
     }
 
-    Ok(HttpResponse::InternalServerError().json(SemanticSearchResponse {
+    Ok((StatusCode::INTERNAL_SERVER_ERROR, SemanticSearchResponse {
         success: false,
         matches: None,
         total_matches: None,
         search_interpretation: None,
         error: Some("Failed to parse Gemini response".to_string()),
         token_usage: None,
+        cached: false,
     }))
 }
 
-/// Call Claude CLI for semantic search
-async fn call_claude_for_search(prompt: &str) -> Result<HttpResponse> {
-    match crate::claude_insights::call_claude_code_cli(prompt, &None).await {
+/// Status for a failed Gemini analysis: configuration problems (400) and withheld or
+/// truncated output (422) keep their status, anything else is the upstream failing (502)
+fn provider_failure_status(gemini_status: StatusCode) -> StatusCode {
+    match gemini_status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => gemini_status,
+        _ => StatusCode::BAD_GATEWAY,
+    }
+}
+
+/// Call Claude (API or CLI backend) for semantic search
+async fn call_claude_for_search(
+    client: &reqwest::Client,
+    backend: &crate::claude_insights::ClaudeBackend,
+    prompt: &str,
+    progress: &Progress,
+) -> Result<(StatusCode, SemanticSearchResponse)> {
+    match crate::claude_insights::call_claude(client, backend, prompt, &None).await {
         Ok((analysis, token_usage)) => {
-            println!("✅ Claude CLI call successful");
+            log::debug!("✅ Claude {} call successful", backend.name());
 
             // Parse AI response
+            progress.emit("parsing", json!({ "characters": analysis.chars().count() }));
             match parse_search_results(&analysis) {
                 Ok((matches, total_matches, interpretation)) => {
-                    Ok(HttpResponse::Ok().json(SemanticSearchResponse {
+                    Ok((StatusCode::OK, SemanticSearchResponse {
                         success: true,
                         matches: Some(matches),
                         total_matches: Some(total_matches),
                         search_interpretation: Some(interpretation),
                         error: None,
                         token_usage: token_usage.map(|u| u.into()),
+                        cached: false,
                     }))
                 }
                 Err(e) => {
-                    eprintln!("❌ Failed to parse AI response: {}", e);
-                    Ok(HttpResponse::Ok().json(SemanticSearchResponse {
+                    log::error!("❌ Failed to parse AI response: {}", e);
+                    Ok((StatusCode::UNPROCESSABLE_ENTITY, SemanticSearchResponse {
                         success: false,
                         matches: None,
                         total_matches: None,
                         search_interpretation: None,
                         error: Some(format!("Failed to parse AI response: {}", e)),
                         token_usage: token_usage.map(|u| u.into()),
+                        cached: false,
                     }))
                 }
             }
         }
         Err(e) => {
-            eprintln!("❌ Claude CLI call failed: {}", e);
-            Ok(HttpResponse::InternalServerError().json(SemanticSearchResponse {
+            log::error!("❌ Claude {} call failed: {}", backend.name(), e);
+            Ok((StatusCode::BAD_GATEWAY, SemanticSearchResponse {
                 success: false,
                 matches: None,
                 total_matches: None,
                 search_interpretation: None,
-                error: Some(format!("Claude CLI error: {}", e)),
+                error: Some(format!("Claude {} error: {}", backend.name().to_uppercase(), e)),
                 token_usage: None,
+                cached: false,
             }))
         }
     }
 }
 
+/// Call OpenAI Chat Completions for semantic search
+async fn call_openai_for_search(
+    client: &reqwest::Client,
+    api_key: &str,
+    prompt: &str,
+    progress: &Progress,
+) -> Result<(StatusCode, SemanticSearchResponse)> {
+    if !openai_insights::is_api_key_configured(api_key) {
+        return Ok((StatusCode::BAD_REQUEST, SemanticSearchResponse {
+            success: false,
+            matches: None,
+            total_matches: None,
+            search_interpretation: None,
+            error: Some(format!("OpenAI provider not configured. {}", openai_insights::API_KEY_SETUP_HINT)),
+            token_usage: None,
+            cached: false,
+        }));
+    }
+
+    match openai_insights::call_openai_json(client, api_key, prompt).await {
+        Ok((analysis, token_usage)) => {
+            progress.emit("parsing", json!({ "characters": analysis.chars().count() }));
+            match parse_search_results(&analysis) {
+                Ok((matches, total_matches, interpretation)) => Ok((StatusCode::OK, SemanticSearchResponse {
+                    success: true,
+                    matches: Some(matches),
+                    total_matches: Some(total_matches),
+                    search_interpretation: Some(interpretation),
+                    error: None,
+                    token_usage: token_usage.map(|u| u.into()),
+                    cached: false,
+                })),
+                Err(e) => {
+                    log::error!("❌ Failed to parse AI response: {}", e);
+                    Ok((StatusCode::UNPROCESSABLE_ENTITY, SemanticSearchResponse {
+                        success: false,
+                        matches: None,
+                        total_matches: None,
+                        search_interpretation: None,
+                        error: Some(format!("Failed to parse AI response: {}", e)),
+                        token_usage: token_usage.map(|u| u.into()),
+                        cached: false,
+                    }))
+                }
+            }
+        }
+        Err(e) => {
+            log::error!("❌ OpenAI call failed: {}", e);
+            Ok((StatusCode::BAD_GATEWAY, SemanticSearchResponse {
+                success: false,
+                matches: None,
+                total_matches: None,
+                search_interpretation: None,
+                error: Some(format!("OpenAI error: {}", e)),
+                token_usage: None,
+                cached: false,
+            }))
+        }
+    }
+}
+
+/// Whether searches should be recorded in the search_log table
+fn search_analytics_enabled() -> bool {
+    std::env::var("SEARCH_ANALYTICS")
+        .map(|value| value.trim().eq_ignore_ascii_case("on"))
+        .unwrap_or(false)
+}
+
+/// One-way hash of the client address so searches can be grouped per user
+/// without storing the address itself. SEARCH_ANALYTICS_SALT makes the hash
+/// impractical to reverse by enumerating addresses.
+fn anonymize_user(client_ip: Option<&str>) -> String {
+    let salt = std::env::var("SEARCH_ANALYTICS_SALT").unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(salt.as_bytes());
+    hasher.update(client_ip.unwrap_or("unknown").as_bytes());
+    hasher.finalize().iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Record a completed search for analytics
+async fn log_search(
+    pool: &Pool<Postgres>,
+    query: &str,
+    provider: &str,
+    response: &SemanticSearchResponse,
+    user_hash: &str,
+) -> std::result::Result<(), sqlx::Error> {
+    let query_text = if crate::pii_redaction_enabled() {
+        crate::redact_pii(query.trim())
+    } else {
+        query.trim().to_string()
+    };
+    let usage = response.token_usage.as_ref();
+
+    sqlx::query(
+        "INSERT INTO search_log (query_text, provider, success, match_count, prompt_tokens, completion_tokens, total_tokens, user_hash)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+    )
+    .bind(query_text)
+    .bind(provider)
+    .bind(response.success)
+    .bind(response.matches.as_ref().map(|m| m.len() as i32))
+    .bind(usage.and_then(|u| u.prompt_tokens).map(|t| t as i32))
+    .bind(usage.and_then(|u| u.completion_tokens).map(|t| t as i32))
+    .bind(usage.and_then(|u| u.total_tokens).map(|t| t as i32))
+    .bind(user_hash)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Query parameters for the analytics endpoint
+#[derive(Debug, Deserialize)]
+pub struct SearchAnalyticsQuery {
+    /// Start of the range (RFC 3339); defaults to `days` before `to`
+    pub from: Option<DateTime<Utc>>,
+
+    /// End of the range (RFC 3339); defaults to now
+    pub to: Option<DateTime<Utc>>,
+
+    /// Length of the default range in days, clamped to 1..=MAX_ANALYTICS_DAYS
+    #[serde(default = "default_analytics_days")]
+    pub days: i64,
+
+    /// Number of top queries to return
+    #[serde(default = "default_top_queries")]
+    pub limit: i64,
+}
+
+fn default_analytics_days() -> i64 {
+    30
+}
+
+/// Longest default range the analytics endpoint will compute (about ten years)
+const MAX_ANALYTICS_DAYS: i64 = 3650;
+
+/// Resolve the requested range, rejecting ranges that are empty or start before the earliest
+/// representable time
+fn analytics_range(query: &SearchAnalyticsQuery) -> Result<(DateTime<Utc>, DateTime<Utc>), &'static str> {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query
+        .from
+        .or_else(|| to.checked_sub_signed(Duration::days(query.days.clamp(1, MAX_ANALYTICS_DAYS))))
+        .ok_or("'to' is too early for the requested number of days")?;
+    if from >= to {
+        return Err("'from' must be earlier than 'to'");
+    }
+    Ok((from, to))
+}
+
+fn default_top_queries() -> i64 {
+    20
+}
+
+/// Admin-only summary of logged searches: top queries and token spend over a time range
+pub async fn get_search_analytics(
+    http_req: HttpRequest,
+    data: web::Data<std::sync::Arc<ApiState>>,
+    query: web::Query<SearchAnalyticsQuery>,
+) -> Result<HttpResponse> {
+    if let Some(denied) = crate::require_admin_key(&http_req) {
+        return Ok(denied);
+    }
+
+    let pool = match &data.db {
+        Some(pool) => pool,
+        None => {
+            return Ok(HttpResponse::build(data.db_unavailable_status()).json(json!({
+                "success": false,
+                "error": data.db_unavailable_message()
+            })));
+        }
+    };
+
+    let (from, to) = match analytics_range(&query) {
+        Ok(range) => range,
+        Err(error) => {
+            return Ok(HttpResponse::BadRequest().json(json!({
+                "success": false,
+                "error": error
+            })));
+        }
+    };
+
+    match fetch_search_analytics(pool, from, to, query.limit.clamp(1, 100)).await {
+        Ok(summary) => Ok(HttpResponse::Ok().json(json!({
+            "success": true,
+            "from": from,
+            "to": to,
+            "analytics_enabled": search_analytics_enabled(),
+            "totals": summary.0,
+            "providers": summary.1,
+            "top_queries": summary.2
+        }))),
+        Err(e) => Ok(HttpResponse::InternalServerError().json(json!({
+            "success": false,
+            "error": format!("Failed to load search analytics: {}", e)
+        }))),
+    }
+}
+
+async fn fetch_search_analytics(
+    pool: &Pool<Postgres>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    limit: i64,
+) -> std::result::Result<(serde_json::Value, Vec<serde_json::Value>, Vec<serde_json::Value>), sqlx::Error> {
+    let totals_row = sqlx::query(
+        "SELECT COUNT(*) AS searches,
+                COUNT(DISTINCT user_hash) AS unique_users,
+                COALESCE(SUM(prompt_tokens), 0)::BIGINT AS prompt_tokens,
+                COALESCE(SUM(completion_tokens), 0)::BIGINT AS completion_tokens,
+                COALESCE(SUM(total_tokens), 0)::BIGINT AS total_tokens
+         FROM search_log WHERE created_at >= $1 AND created_at < $2"
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_one(pool)
+    .await?;
+
+    let totals = json!({
+        "searches": totals_row.get::<i64, _>("searches"),
+        "unique_users": totals_row.get::<i64, _>("unique_users"),
+        "prompt_tokens": totals_row.get::<i64, _>("prompt_tokens"),
+        "completion_tokens": totals_row.get::<i64, _>("completion_tokens"),
+        "total_tokens": totals_row.get::<i64, _>("total_tokens"),
+    });
+
+    let providers = sqlx::query(
+        "SELECT provider, COUNT(*) AS searches, COALESCE(SUM(total_tokens), 0)::BIGINT AS total_tokens
+         FROM search_log WHERE created_at >= $1 AND created_at < $2
+         GROUP BY provider ORDER BY searches DESC"
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|row| json!({
+        "provider": row.get::<String, _>("provider"),
+        "searches": row.get::<i64, _>("searches"),
+        "total_tokens": row.get::<i64, _>("total_tokens"),
+    }))
+    .collect();
+
+    let top_queries = sqlx::query(
+        "SELECT LOWER(query_text) AS query_text,
+                COUNT(*) AS searches,
+                AVG(match_count)::FLOAT8 AS avg_matches,
+                COALESCE(SUM(total_tokens), 0)::BIGINT AS total_tokens,
+                MAX(created_at) AS last_searched
+         FROM search_log WHERE created_at >= $1 AND created_at < $2
+         GROUP BY LOWER(query_text)
+         ORDER BY searches DESC, last_searched DESC
+         LIMIT $3"
+    )
+    .bind(from)
+    .bind(to)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?
+    .iter()
+    .map(|row| json!({
+        "query": row.get::<String, _>("query_text"),
+        "searches": row.get::<i64, _>("searches"),
+        "avg_matches": row.get::<Option<f64>, _>("avg_matches"),
+        "total_tokens": row.get::<i64, _>("total_tokens"),
+        "last_searched": row.get::<DateTime<Utc>, _>("last_searched"),
+    }))
+    .collect();
+
+    Ok((totals, providers, top_queries))
+}
+
 /// Parse AI response and extract search results
 ///
 /// This centralizes response parsing logic on the server,
 /// making it easier to handle different AI response formats
 fn parse_search_results(analysis: &str) -> anyhow::Result<(Vec<SearchMatch>, usize, String)> {
-    // Remove markdown code blocks if present
-    let mut json_text = analysis.to_string();
-    json_text = json_text.replace("```json", "").replace("```", "");
-
-    // Try to find JSON object
-    let json_match = json_text.find('{')
-        .and_then(|start| {
-            json_text.rfind('}').map(|end| &json_text[start..=end])
-        })
+    // Prefer a ```json fenced block, then any complete object found by brace scanning;
+    // among several objects the one carrying a `matches` array is the payload
+    let fenced = fenced_json(analysis).and_then(|block| serde_json::from_str::<serde_json::Value>(block.trim()).ok());
+    let candidates: Vec<serde_json::Value> = fenced.into_iter().chain(json_objects(analysis)).collect();
+    let parsed = candidates
+        .iter()
+        .find(|value| value["matches"].is_array())
+        .or_else(|| candidates.first())
         .ok_or_else(|| anyhow::anyhow!("No JSON found in response"))?;
 
-    // Parse JSON
-    let parsed: serde_json::Value = serde_json::from_str(json_match)?;
-
     // Extract matches array
-    let matches = parsed["matches"]
+    let mut matches = parsed["matches"]
         .as_array()
         .ok_or_else(|| anyhow::anyhow!("No 'matches' array in response"))?
         .iter()
@@ -358,7 +1055,8 @@ fn parse_search_results(analysis: &str) -> anyhow::Result<(Vec<SearchMatch>, usi
             Some(SearchMatch {
                 title: m["title"].as_str()?.to_string(),
                 description: m["description"].as_str()?.to_string(),
-                relevance_score: m["relevance_score"].as_u64().map(|v| v as u32),
+                // Models sometimes emit 150 or -5; scores are percentages
+                relevance_score: m["relevance_score"].as_f64().map(|v| v.clamp(0.0, 100.0).round() as u32),
                 match_reason: m["match_reason"].as_str().map(|s| s.to_string()),
                 url: m["url"].as_str().map(|s| s.to_string()),
                 team: m["team"].as_str().map(|s| s.to_string()),
@@ -366,10 +1064,19 @@ fn parse_search_results(analysis: &str) -> anyhow::Result<(Vec<SearchMatch>, usi
             })
         })
         .collect::<Vec<_>>();
+    // Best first regardless of the model's ordering; unscored matches go last
+    matches.sort_by_key(|m| std::cmp::Reverse(m.relevance_score));
+
+    // The same project echoed twice: keep the first, i.e. best-scored, copy
+    let listed = matches.len();
+    let mut seen = std::collections::HashSet::new();
+    matches.retain(|m| seen.insert(match_key(m)));
+    let duplicates = listed - matches.len();
 
     let total_matches = parsed["total_matches"]
         .as_u64()
-        .unwrap_or(matches.len() as u64) as usize;
+        .map(|total| (total as usize).saturating_sub(duplicates).max(matches.len()))
+        .unwrap_or(matches.len());
 
     let interpretation = parsed["search_interpretation"]
         .as_str()
@@ -379,10 +1086,91 @@ fn parse_search_results(analysis: &str) -> anyhow::Result<(Vec<SearchMatch>, usi
     Ok((matches, total_matches, interpretation))
 }
 
+/// Identity used for dedup: the URL when present, otherwise the case-insensitive title
+fn match_key(m: &SearchMatch) -> String {
+    match m.url.as_deref().map(str::trim).filter(|url| !url.is_empty()) {
+        Some(url) => format!("url:{url}"),
+        None => format!("title:{}", m.title.trim().to_lowercase()),
+    }
+}
+
+/// Contents of the first ```json fenced block, if the response has one
+fn fenced_json(text: &str) -> Option<&str> {
+    let start = text.find("```json")? + "```json".len();
+    let end = text[start..].find("```")?;
+    Some(&text[start..start + end])
+}
+
+/// Every top-level JSON object in `text`, in order. Each `{` is scanned to its balancing
+/// `}` (ignoring braces inside strings); spans that don't parse, such as braces in prose,
+/// are skipped and scanning resumes at the next `{`.
+fn json_objects(text: &str) -> Vec<serde_json::Value> {
+    let mut objects = Vec::new();
+    let mut search_from = 0;
+    while let Some(offset) = text[search_from..].find('{') {
+        let start = search_from + offset;
+        let parsed = balanced_object_end(&text[start..])
+            .and_then(|len| serde_json::from_str(&text[start..start + len]).ok().map(|value| (value, len)));
+        match parsed {
+            Some((value, len)) => {
+                objects.push(value);
+                search_from = start + len;
+            }
+            None => search_from = start + 1,
+        }
+    }
+    objects
+}
+
+/// Byte length of the object starting at `text[0] == '{'`, or None if it never closes
+fn balanced_object_end(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_analytics_range_clamps_days() {
+        let to = DateTime::parse_from_rfc3339("2024-06-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let query = |days: i64, to: Option<DateTime<Utc>>| SearchAnalyticsQuery { from: None, to, days, limit: 20 };
+
+        assert_eq!(analytics_range(&query(7, Some(to))).unwrap().0, to - Duration::days(7));
+        assert_eq!(analytics_range(&query(0, Some(to))).unwrap().0, to - Duration::days(1));
+        assert_eq!(analytics_range(&query(i64::MAX, Some(to))).unwrap().0, to - Duration::days(MAX_ANALYTICS_DAYS));
+        // A range reaching before the earliest representable time is a bad request, not a panic
+        assert!(analytics_range(&query(30, Some(DateTime::<Utc>::MIN_UTC))).is_err());
+
+        let reversed = SearchAnalyticsQuery { from: Some(to), to: Some(to - Duration::days(1)), days: 30, limit: 20 };
+        assert!(analytics_range(&reversed).is_err());
+    }
+
     #[test]
     fn test_parse_search_results() {
         let response = r#"{
@@ -424,6 +1212,110 @@ mod tests {
         assert_eq!(total, 0);
     }
 
+    #[test]
+    fn test_parse_search_results_after_prose_with_a_brace() {
+        let response = r#"I looked for projects matching {solar and the results are below.
+        {"matches": [{"title": "Green Energy", "description": "Solar project"}], "total_matches": 1, "search_interpretation": "Solar"}"#;
+
+        let (matches, total, interp) = parse_search_results(response).unwrap();
+        assert_eq!(matches[0].title, "Green Energy");
+        assert_eq!(total, 1);
+        assert_eq!(interp, "Solar");
+    }
+
+    #[test]
+    fn test_parse_search_results_picks_the_results_object() {
+        let response = r#"Parsed query: {"keywords": ["water", "{filters}"]}
+        Results: {"matches": [{"title": "Clean Water", "description": "Filtration"}], "total_matches": 1, "search_interpretation": "Water"}
+        Done."#;
+
+        let (matches, _, interp) = parse_search_results(response).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].title, "Clean Water");
+        assert_eq!(interp, "Water");
+    }
+
+    #[test]
+    fn test_relevance_scores_are_clamped_sorted_and_filtered() {
+        let response = r#"{
+            "matches": [
+                {"title": "Low", "description": "d", "relevance_score": -20},
+                {"title": "Unscored", "description": "d"},
+                {"title": "Over", "description": "d", "relevance_score": 150},
+                {"title": "Mid", "description": "d", "relevance_score": 60}
+            ],
+            "total_matches": 4,
+            "search_interpretation": "Test"
+        }"#;
+
+        let (matches, total, interpretation) = parse_search_results(response).unwrap();
+        let scored: Vec<_> = matches.iter().map(|m| (m.title.as_str(), m.relevance_score)).collect();
+        assert_eq!(scored, [("Over", Some(100)), ("Mid", Some(60)), ("Low", Some(0)), ("Unscored", None)]);
+
+        let mut response = SemanticSearchResponse {
+            success: true,
+            matches: Some(matches),
+            total_matches: Some(total),
+            search_interpretation: Some(interpretation),
+            error: None,
+            token_usage: None,
+            cached: false,
+        };
+        apply_min_relevance(&mut response, 50);
+        let titles: Vec<_> = response.matches.unwrap().into_iter().map(|m| m.title).collect();
+        assert_eq!(titles, ["Over", "Mid", "Unscored"]);
+        assert_eq!(response.total_matches, Some(3));
+    }
+
+    #[test]
+    fn test_duplicate_matches_are_merged() {
+        let response = r#"{
+            "matches": [
+                {"title": "Solar Co-op", "description": "d", "url": "https://example.com/solar", "relevance_score": 70},
+                {"title": "Solar Coop (copy)", "description": "d", "url": "https://example.com/solar", "relevance_score": 90},
+                {"title": "Wind Farm", "description": "d", "relevance_score": 50},
+                {"title": "wind farm ", "description": "d", "relevance_score": 40}
+            ],
+            "total_matches": 4,
+            "search_interpretation": "Energy"
+        }"#;
+
+        let (matches, total, _) = parse_search_results(response).unwrap();
+        let kept: Vec<_> = matches.iter().map(|m| (m.title.as_str(), m.relevance_score)).collect();
+        assert_eq!(kept, [("Solar Coop (copy)", Some(90)), ("Wind Farm", Some(50))]);
+        assert_eq!(total, 2);
+    }
+
+    #[test]
+    fn test_keyword_search_ranks_by_matched_terms() {
+        let project = |title: &str, description: &str| ProjectData {
+            title: title.to_string(),
+            description: description.to_string(),
+            team: None,
+            status: None,
+            tags: None,
+            url: None,
+        };
+        let projects = vec![
+            project("Community Garden", "Urban farming"),
+            project("Rooftop Solar", "Solar panels for schools"),
+            project("Battery Storage", "Storage for solar and wind energy"),
+        ];
+
+        let response = keyword_search("solar energy projects", &projects, 10);
+        let matches = response.matches.unwrap();
+        let ranked: Vec<_> = matches.iter().map(|m| (m.title.as_str(), m.relevance_score, m.match_reason.as_deref())).collect();
+        assert_eq!(ranked, [
+            ("Battery Storage", Some(100), Some("matched terms: solar, energy")),
+            ("Rooftop Solar", Some(50), Some("matched term: solar")),
+        ]);
+        assert_eq!(response.total_matches, Some(2));
+
+        // The AI path sees the same ranking, padded with the non-matching projects
+        let selected = select_projects_for_analysis("solar", &projects, 2);
+        assert_eq!(selected.iter().map(|p| p.title.as_str()).collect::<Vec<_>>(), ["Rooftop Solar", "Battery Storage"]);
+    }
+
     #[test]
     fn test_apply_filters() {
         let projects = vec![
@@ -449,10 +1341,104 @@ mod tests {
             max_results: 30,
             teams: Some(vec!["Engineering".to_string()]),
             status: None,
+            min_relevance: None,
         };
 
         let filtered = apply_filters(&projects, &filters);
         assert_eq!(filtered.len(), 1);
         assert_eq!(filtered[0].title, "Project A");
     }
+
+    #[tokio::test]
+    async fn test_load_projects_limits_and_filters_by_status() {
+        let Some(pool) = crate::tests::test_pool().await else { return };
+        crate::init_database(&pool).await.unwrap();
+
+        // A status unique to this run keeps other rows out of the result
+        let status = format!("search-{}", &uuid::Uuid::new_v4().simple().to_string()[..12]);
+        for (name, minutes_ago, deleted) in [("Oldest", 30, false), ("Middle", 20, false), ("Newest", 10, false), ("Removed", 0, true)] {
+            sqlx::query(
+                "INSERT INTO projects (name, description, status, date_modified, deleted)
+                 VALUES ($1, 'desc', $2, NOW() - make_interval(mins => $3), $4)"
+            )
+            .bind(name)
+            .bind(&status)
+            .bind(minutes_ago)
+            .bind(deleted)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let filters = SearchFilters { max_results: 2, teams: None, status: Some(vec![status.clone()]), min_relevance: None };
+        let projects = load_projects(&pool, &filters).await.unwrap();
+        let titles: Vec<_> = projects.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, ["Newest", "Middle"]);
+        assert_eq!(projects[0].status.as_deref(), Some(status.as_str()));
+
+        sqlx::query("DELETE FROM projects WHERE status = $1").bind(&status).execute(&pool).await.unwrap();
+    }
+
+    #[test]
+    fn test_provider_failure_status() {
+        assert_eq!(provider_failure_status(StatusCode::INTERNAL_SERVER_ERROR), StatusCode::BAD_GATEWAY);
+        assert_eq!(provider_failure_status(StatusCode::SERVICE_UNAVAILABLE), StatusCode::BAD_GATEWAY);
+        assert_eq!(provider_failure_status(StatusCode::BAD_REQUEST), StatusCode::BAD_REQUEST);
+        assert_eq!(provider_failure_status(StatusCode::UNPROCESSABLE_ENTITY), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_openai_provider_requires_a_key() {
+        let (status, response) = call_openai_for_search(&reqwest::Client::new(), "", "prompt", &Progress::default()).await.unwrap();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(response.error.unwrap().starts_with("OpenAI provider not configured"));
+    }
+
+    #[test]
+    fn test_search_cache_keys_and_eviction() {
+        let projects = vec![ProjectData {
+            title: "Rooftop Solar".to_string(),
+            description: "Panels".to_string(),
+            team: None,
+            status: None,
+            tags: None,
+            url: None,
+        }];
+        let key = cache_key("Solar  projects", "gemini", &projects, &SearchFilters::default());
+        assert_eq!(key, cache_key(" solar projects ", "gemini", &projects, &SearchFilters::default()));
+        assert_ne!(key, cache_key("solar projects", "claude", &projects, &SearchFilters::default()));
+        assert_ne!(key, cache_key("solar projects", "gemini", &[], &SearchFilters::default()));
+        let filters = SearchFilters { min_relevance: Some(50), ..SearchFilters::default() };
+        assert_ne!(key, cache_key("solar projects", "gemini", &projects, &filters));
+
+        let response = |interpretation: &str| SemanticSearchResponse {
+            success: true,
+            matches: Some(Vec::new()),
+            total_matches: Some(0),
+            search_interpretation: Some(interpretation.to_string()),
+            error: None,
+            token_usage: None,
+            cached: false,
+        };
+        let ttl = std::time::Duration::from_secs(60);
+        let cache = SearchCache::default();
+        for i in 0..SEARCH_CACHE_MAX_ENTRIES {
+            cache.insert(format!("key-{i}"), response(&i.to_string()), ttl);
+        }
+        // Reading key-0 makes key-1 the least recently used
+        assert_eq!(cache.get("key-0", ttl).unwrap().search_interpretation.as_deref(), Some("0"));
+        cache.insert("new".to_string(), response("new"), ttl);
+        assert!(cache.get("key-1", ttl).is_none());
+        assert!(cache.get("key-0", ttl).is_some());
+        assert!(cache.get("new", std::time::Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn test_anonymize_user_is_stable_and_opaque() {
+        let first = anonymize_user(Some("203.0.113.7"));
+        assert_eq!(first, anonymize_user(Some("203.0.113.7")));
+        assert_ne!(first, anonymize_user(Some("203.0.113.8")));
+        assert!(!first.contains("203.0.113.7"));
+        assert_eq!(first.len(), 16);
+    }
 }
@@ -0,0 +1,291 @@
+// src/sessions.rs
+// Login session storage: in memory by default, or in Postgres with SESSION_STORE=db
+
+use crate::oauth::UserSession;
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Cookie carrying the session id; clients without cookies can send it as x-session-id
+pub const SESSION_COOKIE: &str = "pt_session";
+/// How often expired sessions are purged
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// Session lifetime unless SESSION_TTL_HOURS says otherwise
+const DEFAULT_SESSION_TTL_HOURS: u64 = 24;
+
+#[derive(Clone)]
+enum Backend {
+    /// Lost on restart and private to this process; fine for single-instance development
+    Memory(Arc<Mutex<HashMap<String, UserSession>>>),
+    /// Survives restarts and is shared by every instance using the database. Provider
+    /// access tokens are not written to the table; they stay in this process, so calls
+    /// made with the user's token only work on the instance that handled the sign-in.
+    Database(Pool<Postgres>, Arc<Mutex<HashMap<String, String>>>),
+}
+
+/// Key a session is stored under: SHA-256 of its id, so a copy of the table (or of the
+/// memory map) does not hold ids that can be replayed as cookies
+fn storage_key(id: &str) -> String {
+    format!("{:x}", Sha256::digest(id.as_bytes()))
+}
+
+/// Sessions keyed by an opaque token. A session is logged out once it passes its own
+/// `expires_at` or becomes older than the store's TTL, whichever comes first.
+#[derive(Clone)]
+pub struct SessionStore {
+    backend: Backend,
+    ttl: Duration,
+}
+
+/// SESSION_TTL_HOURS, defaulting to 24
+pub fn session_ttl() -> Duration {
+    let hours = std::env::var("SESSION_TTL_HOURS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|hours| *hours > 0)
+        .unwrap_or(DEFAULT_SESSION_TTL_HOURS);
+    Duration::from_secs(hours * 3600)
+}
+
+impl SessionStore {
+    pub fn memory() -> Self {
+        Self::with_ttl(Backend::Memory(Arc::new(Mutex::new(HashMap::new()))), session_ttl())
+    }
+
+    fn with_ttl(backend: Backend, ttl: Duration) -> Self {
+        SessionStore { backend, ttl }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn is_live(&self, session: &UserSession) -> bool {
+        let age = chrono::Utc::now().timestamp() - session.created_at;
+        !session.is_expired() && age < self.ttl.as_secs() as i64
+    }
+
+    /// Pick the store from SESSION_STORE ("memory" or "db"). Asking for the database
+    /// store without a connection falls back to memory with a warning.
+    pub async fn from_env(db: Option<&Pool<Postgres>>) -> anyhow::Result<Self> {
+        let wants_db = std::env::var("SESSION_STORE")
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "db" | "database" | "postgres"))
+            .unwrap_or(false);
+        if !wants_db {
+            return Ok(Self::memory());
+        }
+
+        let Some(pool) = db else {
+            log::warn!("SESSION_STORE=db but no database connection; sessions will be kept in memory");
+            return Ok(Self::memory());
+        };
+        // The table comes from the migrations; refuse to start rather than fail every sign-in
+        let table_exists: bool = sqlx::query_scalar("SELECT to_regclass('public.sessions') IS NOT NULL")
+            .fetch_one(pool)
+            .await?;
+        if !table_exists {
+            anyhow::bail!("SESSION_STORE=db but the sessions table is missing; run `init-db` or start with RUN_MIGRATIONS=true");
+        }
+        Ok(Self::with_ttl(Backend::Database(pool.clone(), Arc::default()), session_ttl()))
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self.backend {
+            Backend::Memory(_) => "memory",
+            Backend::Database(..) => "db",
+        }
+    }
+
+    /// Store a session and return its new id
+    pub async fn create(&self, session: &UserSession) -> Result<String, sqlx::Error> {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let key = storage_key(&id);
+        match &self.backend {
+            Backend::Memory(sessions) => {
+                sessions.lock().unwrap().insert(key, session.clone());
+            }
+            Backend::Database(pool, access_tokens) => {
+                sqlx::query(
+                    "INSERT INTO sessions (id, user_json, provider, created_at, expires_at)
+                     VALUES ($1, $2, $3, to_timestamp($4), to_timestamp($5))"
+                )
+                .bind(&key)
+                .bind(serde_json::to_value(session.public()).unwrap_or_default())
+                .bind(&session.provider)
+                .bind(session.created_at as f64)
+                .bind(session.expires_at as f64)
+                .execute(pool)
+                .await?;
+                if let Some(token) = &session.access_token {
+                    access_tokens.lock().unwrap().insert(key, token.clone());
+                }
+            }
+        }
+        Ok(id)
+    }
+
+    /// Look up a live session; expired ones are treated as missing
+    pub async fn get(&self, id: &str) -> Result<Option<UserSession>, sqlx::Error> {
+        let key = storage_key(id);
+        match &self.backend {
+            Backend::Memory(sessions) => {
+                Ok(sessions.lock().unwrap().get(&key).filter(|s| self.is_live(s)).cloned())
+            }
+            Backend::Database(pool, access_tokens) => {
+                let user_json: Option<serde_json::Value> = sqlx::query_scalar(
+                    "SELECT user_json FROM sessions
+                     WHERE id = $1 AND expires_at > NOW() AND created_at > NOW() - make_interval(secs => $2)"
+                )
+                .bind(&key)
+                .bind(self.ttl.as_secs() as f64)
+                .fetch_optional(pool)
+                .await?;
+                let session = user_json.and_then(|json| serde_json::from_value::<UserSession>(json).ok());
+                Ok(session.map(|mut session| {
+                    session.access_token = access_tokens.lock().unwrap().get(&key).cloned();
+                    session
+                }))
+            }
+        }
+    }
+
+    pub async fn remove(&self, id: &str) -> Result<(), sqlx::Error> {
+        let key = storage_key(id);
+        match &self.backend {
+            Backend::Memory(sessions) => {
+                sessions.lock().unwrap().remove(&key);
+            }
+            Backend::Database(pool, access_tokens) => {
+                sqlx::query("DELETE FROM sessions WHERE id = $1").bind(&key).execute(pool).await?;
+                access_tokens.lock().unwrap().remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop expired sessions, returning how many were removed
+    pub async fn cleanup_expired(&self) -> Result<u64, sqlx::Error> {
+        match &self.backend {
+            Backend::Memory(sessions) => {
+                let mut sessions = sessions.lock().unwrap();
+                let before = sessions.len();
+                sessions.retain(|_, s| self.is_live(s));
+                Ok((before - sessions.len()) as u64)
+            }
+            Backend::Database(pool, access_tokens) => {
+                let removed: Vec<String> = sqlx::query_scalar(
+                    "DELETE FROM sessions WHERE expires_at <= NOW() OR created_at <= NOW() - make_interval(secs => $1)
+                     RETURNING id"
+                )
+                .bind(self.ttl.as_secs() as f64)
+                .fetch_all(pool)
+                .await?;
+                let mut access_tokens = access_tokens.lock().unwrap();
+                for key in &removed {
+                    access_tokens.remove(key);
+                }
+                Ok(removed.len() as u64)
+            }
+        }
+    }
+
+    /// Purge expired sessions in the background for the life of the server
+    pub fn spawn_cleanup(&self) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                match store.cleanup_expired().await {
+                    Ok(0) => {}
+                    Ok(removed) => log::info!("Removed {removed} expired sessions"),
+                    Err(e) => log::warn!("Session cleanup failed: {e}"),
+                }
+            }
+        });
+    }
+}
+
+/// Session id from the session cookie or the x-session-id header
+pub fn session_id(req: &actix_web::HttpRequest) -> Option<String> {
+    req.cookie(SESSION_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+        .or_else(|| req.headers().get("x-session-id").and_then(|v| v.to_str().ok()).map(|v| v.to_string()))
+        .filter(|id| !id.is_empty())
+}
+
+/// HttpOnly cookie holding the session id, expiring with the session TTL
+pub fn session_cookie(id: &str, ttl: Duration) -> actix_web::cookie::Cookie<'static> {
+    actix_web::cookie::Cookie::build(SESSION_COOKIE, id.to_string())
+        .path("/")
+        .http_only(true)
+        .same_site(actix_web::cookie::SameSite::Lax)
+        .max_age(actix_web::cookie::time::Duration::seconds(ttl.as_secs() as i64))
+        .finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(expires_in: i64) -> UserSession {
+        let mut session = UserSession::new("u1".into(), "u1@example.org".into(), "User One".into(), None, "demo".into());
+        session.expires_at = session.created_at + expires_in;
+        session
+    }
+
+    async fn exercise(store: SessionStore) {
+        let live = store.create(&session(3600)).await.unwrap();
+        let expired = store.create(&session(-10)).await.unwrap();
+
+        assert_eq!(store.get(&live).await.unwrap().unwrap().email, "u1@example.org");
+        assert!(store.get(&expired).await.unwrap().is_none());
+        assert!(store.cleanup_expired().await.unwrap() >= 1);
+
+        store.remove(&live).await.unwrap();
+        assert!(store.get(&live).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        exercise(SessionStore::memory()).await;
+    }
+
+    #[tokio::test]
+    async fn test_sessions_older_than_ttl_are_logged_out() {
+        let store = SessionStore::with_ttl(Backend::Memory(Arc::new(Mutex::new(HashMap::new()))), Duration::from_secs(3600));
+        let mut stale = session(24 * 3600);
+        stale.created_at -= 2 * 3600;
+        let stale = store.create(&stale).await.unwrap();
+        let fresh = store.create(&session(24 * 3600)).await.unwrap();
+
+        assert!(store.get(&stale).await.unwrap().is_none());
+        assert!(store.get(&fresh).await.unwrap().is_some());
+        assert_eq!(store.cleanup_expired().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_database_store() {
+        let Some(pool) = crate::tests::test_pool().await else { return };
+        crate::init_database(&pool).await.unwrap();
+        exercise(SessionStore::with_ttl(Backend::Database(pool.clone(), Arc::default()), session_ttl())).await;
+
+        // The table holds neither the id handed to the client nor the provider token
+        let store = SessionStore::with_ttl(Backend::Database(pool.clone(), Arc::default()), session_ttl());
+        let mut signed_in = session(3600);
+        signed_in.access_token = Some("ya29.secret".to_string());
+        let id = store.create(&signed_in).await.unwrap();
+        let (stored_id, user_json): (String, serde_json::Value) =
+            sqlx::query_as("SELECT id, user_json FROM sessions WHERE id = $1")
+                .bind(storage_key(&id))
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_ne!(stored_id, id);
+        assert!(user_json.get("access_token").is_none());
+        assert_eq!(store.get(&id).await.unwrap().unwrap().access_token.as_deref(), Some("ya29.secret"));
+        store.remove(&id).await.unwrap();
+    }
+}
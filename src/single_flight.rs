@@ -0,0 +1,126 @@
+// src/single_flight.rs
+// Coalesces concurrent identical outbound fetches so a burst of cache misses does the work once
+
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+type InFlight<T> = Shared<BoxFuture<'static, T>>;
+
+/// Calls made with the same key while one is already running await that call's
+/// result instead of starting their own
+pub struct SingleFlight<T: Clone> {
+    in_flight: Arc<Mutex<HashMap<String, InFlight<T>>>>,
+}
+
+impl<T: Clone> Default for SingleFlight<T> {
+    fn default() -> Self {
+        SingleFlight {
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> SingleFlight<T> {
+    pub async fn run<F, Fut>(&self, key: String, fetch: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        let flight = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(flight) => flight.clone(),
+                None => {
+                    // A finished flight removes its own entry; one whose callers all
+                    // disconnected first is removed by the last Waiter to drop
+                    let registry = Arc::clone(&self.in_flight);
+                    let registry_key = key.clone();
+                    let work = fetch();
+                    let flight = async move {
+                        let result = work.await;
+                        registry.lock().unwrap().remove(&registry_key);
+                        result
+                    }
+                    .boxed()
+                    .shared();
+                    in_flight.insert(key.clone(), flight.clone());
+                    flight
+                }
+            }
+        };
+        let mut waiter = Waiter { registry: &self.in_flight, key, flight: Some(flight) };
+        waiter.flight.as_mut().expect("a waiter's flight is only taken when it drops").await
+    }
+}
+
+/// One caller's handle on a flight
+struct Waiter<'a, T: Clone> {
+    registry: &'a Mutex<HashMap<String, InFlight<T>>>,
+    key: String,
+    // Taken in drop so the handle is released while the registry lock is held
+    flight: Option<InFlight<T>>,
+}
+
+impl<T: Clone> Drop for Waiter<'_, T> {
+    fn drop(&mut self) {
+        // Release our handle under the lock, so waiters dropping at the same time see each
+        // other's counts. If only the registry's copy is left and the flight hasn't finished,
+        // nobody else is waiting: drop the entry rather than leave an abandoned fetch for the
+        // next caller to resume.
+        let mut in_flight = self.registry.lock().unwrap();
+        let Some(flight) = self.flight.take() else { return };
+        let ours = in_flight.get(&self.key).is_some_and(|entry| entry.ptr_eq(&flight));
+        drop(flight);
+        if ours && in_flight.get(&self.key).is_some_and(|entry| entry.strong_count() == Some(1)) {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one_fetch() {
+        let flights = SingleFlight::<String>::default();
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let call = |key: &str| {
+            let fetches = Arc::clone(&fetches);
+            flights.run(key.to_string(), move || async move {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                "page".to_string()
+            })
+        };
+
+        let results = futures_util::future::join_all((0..5).map(|_| call("GET https://example.com"))).await;
+        assert_eq!(results, vec!["page"; 5]);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Finished flights are forgotten, and different keys never share
+        call("GET https://example.com").await;
+        call("GET https://example.org").await;
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_flight_is_forgotten() {
+        let flights = SingleFlight::<String>::default();
+        let stalled = || flights.run("GET https://stalled.example".to_string(), futures_util::future::pending);
+
+        let (first, second) = (stalled(), stalled());
+        let both = futures_util::future::join(first, second);
+        assert!(tokio::time::timeout(Duration::from_millis(20), both).await.is_err());
+        assert!(flights.in_flight.lock().unwrap().is_empty());
+
+        // The next caller starts a fresh fetch instead of joining the abandoned one
+        let result = flights.run("GET https://stalled.example".to_string(), || async { "page".to_string() }).await;
+        assert_eq!(result, "page");
+    }
+}